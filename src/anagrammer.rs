@@ -0,0 +1,1615 @@
+//! a library-level facade over trie construction and anagram discovery
+
+use cache::{Cache, CacheStats};
+use count::{self, Count, Counter};
+use dictionary;
+use error::RanaError;
+use factory::{self, RankedWorkerFun, WorkerFun};
+use letter_order::LetterOrder;
+use memo::Memo;
+use order::Order;
+use prune::{PruneStats, Pruner};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{thread_rng, SeedableRng};
+use regex::Regex;
+use score::{Combiner, Tally};
+use std::cmp;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use strategy::Strategy;
+use template::Template;
+use trie::{Extensions, Trie, DEFAULT_WEIGHT};
+use util::{normalize, CharCount, ToDo, Translator, BLANK};
+
+pub use dictionary::Dictionary;
+
+/// Collects the configuration necessary to produce an `Anagrammer`. This
+/// does everything the `rana` executable does before it starts looking for
+/// anagrams.
+#[derive(Debug, Clone)]
+pub struct AnagrammerBuilder {
+    dictionary: Option<Dictionary>,
+    phrase: Vec<String>,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    minimum_word_length: usize,
+    maximum_word_length: Option<usize>,
+    minimum_words: usize,
+    maximum_words: Option<usize>,
+    pattern: Option<String>,
+    required: Vec<(String, usize)>,
+    forbidden: Vec<String>,
+    leftover: Option<usize>,
+    use_at_least: Option<usize>,
+    threads: usize,
+    use_cache: bool,
+    cache_memory: Option<usize>,
+    memo_file: Option<String>,
+    shuffle: bool,
+    seed: Option<u64>,
+    prune: bool,
+    strategy: Strategy,
+    letter_order: LetterOrder,
+}
+
+impl Default for AnagrammerBuilder {
+    fn default() -> Self {
+        AnagrammerBuilder::new()
+    }
+}
+
+impl AnagrammerBuilder {
+    /// Begins a builder with no dictionary or phrase, a minimum word length
+    /// of 1, one thread per core, caching and pruning on, and shuffling off.
+    pub fn new() -> AnagrammerBuilder {
+        AnagrammerBuilder {
+            dictionary: None,
+            phrase: vec![],
+            include: vec![],
+            exclude: vec![],
+            minimum_word_length: 1,
+            maximum_word_length: None,
+            minimum_words: 1,
+            maximum_words: None,
+            pattern: None,
+            required: vec![],
+            forbidden: vec![],
+            leftover: None,
+            use_at_least: None,
+            threads: num_cpus::get(),
+            use_cache: true,
+            cache_memory: None,
+            memo_file: None,
+            shuffle: false,
+            seed: None,
+            prune: true,
+            strategy: Strategy::SortKey,
+            letter_order: LetterOrder::Frequency,
+        }
+    }
+    /// Read words from this line-delimited file.
+    pub fn dictionary_file(mut self, path: &str) -> Self {
+        self.dictionary = Some(Dictionary::File(path.to_string()));
+        self
+    }
+    /// Use these words rather than reading a file.
+    pub fn dictionary_words<S: AsRef<str>, I: IntoIterator<Item = S>>(mut self, words: I) -> Self {
+        self.dictionary = Some(Dictionary::Words(
+            words.into_iter().map(|w| w.as_ref().to_string()).collect(),
+        ));
+        self
+    }
+    /// Add a word to the phrase to anagramize. This may be called repeatedly.
+    /// Each `?` in the word is a blank, which may stand for any one letter.
+    pub fn phrase(mut self, word: &str) -> Self {
+        self.phrase.push(word.to_string());
+        self
+    }
+    /// Require this word in every anagram.
+    pub fn include(mut self, word: &str) -> Self {
+        self.include.push(word.to_string());
+        self
+    }
+    /// Never use this word in an anagram.
+    pub fn exclude(mut self, word: &str) -> Self {
+        self.exclude.push(word.to_string());
+        self
+    }
+    /// Words in anagrams must be at least this long.
+    pub fn minimum_word_length(mut self, n: usize) -> Self {
+        self.minimum_word_length = n;
+        self
+    }
+    /// Words in anagrams may be at most this long. By default they may be
+    /// any length.
+    pub fn maximum_word_length(mut self, n: usize) -> Self {
+        self.maximum_word_length = Some(n);
+        self
+    }
+    /// Anagrams, counting included words, must have at least this many
+    /// words. This does not affect `strict_words_in` or `proven_words_in`.
+    pub fn minimum_words(mut self, n: usize) -> Self {
+        self.minimum_words = n;
+        self
+    }
+    /// Anagrams, counting included words, may have at most this many words.
+    /// By default they may have any number. This does not affect
+    /// `strict_words_in` or `proven_words_in`.
+    pub fn maximum_words(mut self, n: usize) -> Self {
+        self.maximum_words = Some(n);
+        self
+    }
+    /// Anagrams must fit this `Template`, such as "5 3 _", and list their
+    /// words in its order. This may not be combined with included words or
+    /// limits on the number of words.
+    pub fn pattern(mut self, template: &str) -> Self {
+        self.pattern = Some(template.to_string());
+        self
+    }
+    /// Anagrams must have at least `count` words matching this regular
+    /// expression, counting included words. This may be called repeatedly.
+    pub fn require_match(mut self, pattern: &str, count: usize) -> Self {
+        self.required.push((pattern.to_string(), count));
+        self
+    }
+    /// Never use a word matching this regular expression in an anagram. This
+    /// may be called repeatedly.
+    pub fn forbid_match(mut self, pattern: &str) -> Self {
+        self.forbidden.push(pattern.to_string());
+        self
+    }
+    /// Accept anagrams which leave as many as this many letters of the
+    /// phrase unused. By default anagrams use every letter.
+    pub fn leftover(mut self, n: usize) -> Self {
+        self.leftover = Some(n);
+        self
+    }
+    /// Accept anagrams which use at least this percentage of the letters of
+    /// the phrase. If `leftover` is also given, the stricter limit applies.
+    pub fn use_at_least(mut self, percent: usize) -> Self {
+        self.use_at_least = Some(percent);
+        self
+    }
+    /// The number of threads to use during anagram collection.
+    pub fn threads(mut self, n: usize) -> Self {
+        self.threads = n;
+        self
+    }
+    /// Whether to cache partial results. This saves time and costs memory.
+    pub fn cache(mut self, use_cache: bool) -> Self {
+        self.use_cache = use_cache;
+        self
+    }
+    /// Whether to remember the residuals no words can complete, so the
+    /// search need not explore them again however it reaches them. This
+    /// saves time and costs memory. It has no effect if anagrams are limited
+    /// by their number of words, a pattern, required matches, or leftovers.
+    pub fn prune(mut self, prune: bool) -> Self {
+        self.prune = prune;
+        self
+    }
+    /// How to avoid finding the same anagram more than once. The rarest
+    /// letter strategy cannot be combined with a pattern, blanks, or
+    /// leftovers.
+    pub fn strategy(mut self, strategy: Strategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+    /// The order in which to number the letters of the dictionary. This
+    /// decides which letter the rarest letter strategy takes as rarest, and
+    /// may make the search faster or slower. A compiled dictionary in
+    /// another order is renumbered when it is read.
+    pub fn letter_order(mut self, order: LetterOrder) -> Self {
+        self.letter_order = order;
+        self
+    }
+    /// Limit the cache to about this many bytes, evicting entries as needed.
+    /// By default the cache grows without limit.
+    pub fn cache_memory(mut self, bytes: usize) -> Self {
+        self.cache_memory = Some(bytes);
+        self
+    }
+    /// Restore the cache from this file, if it holds entries for the same
+    /// dictionary and configuration. `Anagrammer::save_memo` saves any new
+    /// entries to it. This has no effect if caching is off.
+    pub fn memo_file(mut self, path: &str) -> Self {
+        self.memo_file = Some(path.to_string());
+        self
+    }
+    /// Whether to (partially) shuffle the order of discovery.
+    pub fn shuffle(mut self, shuffle: bool) -> Self {
+        self.shuffle = shuffle;
+        self
+    }
+    /// Derive the shuffles of the order of discovery, and the choices of
+    /// `Anagrammer::sample`, from this seed, so the same seed, phrase, and
+    /// dictionary give the same anagrams in the same order, whatever the
+    /// number of threads. A seeded shuffled search lists no anagram
+    /// beginning with a word until it has found all of them.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+    /// Reads the dictionary, converts the phrase into a `CharCount`, removes
+    /// the included words, and prepares a trie containing only the words
+    /// composable from what remains.
+    pub fn build(self) -> Result<Anagrammer, RanaError> {
+        if self.minimum_word_length == 0 {
+            return Err(RanaError::invalid(
+                "minimum word length",
+                "must be positive",
+            ));
+        }
+        if let Some(n) = self.maximum_word_length {
+            if n < self.minimum_word_length {
+                return Err(RanaError::invalid(
+                    "maximum word length",
+                    "must be at least the minimum word length",
+                ));
+            }
+        }
+        if let Some(n) = self.maximum_words {
+            if n == 0 || n < self.minimum_words {
+                return Err(RanaError::invalid(
+                    "maximum words",
+                    "must be positive and at least the minimum words",
+                ));
+            }
+        }
+        if self.use_at_least.is_some_and(|n| n > 100) {
+            return Err(RanaError::invalid(
+                "use at least",
+                "must be a percentage no greater than 100",
+            ));
+        }
+        if self.threads == 0 {
+            return Err(RanaError::invalid("thread count", "must be positive"));
+        }
+        let template = match self.pattern {
+            Some(ref pattern) => {
+                if !self.include.is_empty()
+                    || self.minimum_words > 1
+                    || self.maximum_words.is_some()
+                {
+                    return Err(RanaError::invalid(
+                        "pattern",
+                        "cannot be combined with included words or word counts",
+                    ));
+                }
+                let template = pattern
+                    .parse::<Template>()
+                    .map_err(|why| RanaError::invalid("pattern", &why))?;
+                Some(template)
+            }
+            None => None,
+        };
+        // a template may limit the lengths of words further
+        let (shortest, longest) = match template {
+            Some(ref template) => (
+                cmp::max(self.minimum_word_length, template.shortest()),
+                match (self.maximum_word_length, template.longest()) {
+                    (Some(a), Some(b)) => Some(cmp::min(a, b)),
+                    (a, b) => a.or(b),
+                },
+            ),
+            None => (self.minimum_word_length, self.maximum_word_length),
+        };
+        let mut required = Vec::with_capacity(self.required.len());
+        for &(ref pattern, count) in &self.required {
+            if count == 0 {
+                return Err(RanaError::invalid(
+                    "required match",
+                    "count must be positive",
+                ));
+            }
+            let re = Regex::new(pattern)
+                .map_err(|why| RanaError::invalid("required match", &why.to_string()))?;
+            required.push((re, count));
+        }
+        let mut forbidden = Vec::with_capacity(self.forbidden.len());
+        for pattern in &self.forbidden {
+            let re = Regex::new(pattern)
+                .map_err(|why| RanaError::invalid("forbidden match", &why.to_string()))?;
+            forbidden.push(re);
+        }
+        let trie = self.make_trie()?;
+
+        // create initial character count
+        let mut cc = trie
+            .translator
+            .count(&normalize(""))
+            .expect("no luck with the char count");
+        // add all the words to anagramize
+        for word in &self.phrase {
+            match trie.translator.translate(word) {
+                Some(usizes) => {
+                    if !cc.add(usizes) {
+                        return Err(dictionary_error(word, &trie));
+                    }
+                }
+                None => return Err(dictionary_error(word, &trie)),
+            }
+            cc.add_blanks(word.chars().filter(|&c| c == BLANK).count());
+        }
+        let mut phrase = cc.clone();
+        phrase.set_limits();
+        // the letters of the phrase which may go unused
+        let leftover = match (self.leftover, self.use_at_least) {
+            (None, None) => 0,
+            (n, percent) => {
+                let spare = percent.map_or(cc.sum, |p| cc.sum - (cc.sum * p).div_ceil(100));
+                cmp::min(n.unwrap_or(cc.sum), spare)
+            }
+        };
+        if self.strategy == Strategy::RarestLetter
+            && (template.is_some() || cc.blanks > 0 || leftover > 0)
+        {
+            return Err(RanaError::invalid(
+                "strategy",
+                "rarest-letter cannot be combined with patterns, blanks, or leftovers",
+            ));
+        }
+        let mut memo = match self.memo_file {
+            Some(ref path) if self.use_cache => Some(Memo::new(
+                path,
+                &trie,
+                shortest,
+                longest,
+                &forbidden,
+                leftover > 0,
+            )),
+            _ => None,
+        };
+        // subtract the words to include
+        for word in &self.include {
+            match trie.translator.translate(word) {
+                Some(usizes) => {
+                    if let Some((index, copy)) = cc.subtract(usizes) {
+                        return Err(RanaError::UnavailableCharacter {
+                            word: trie.translator.etalsnart(&copy).unwrap(),
+                            index,
+                        });
+                    }
+                }
+                None => return Err(dictionary_error(word, &trie)),
+            }
+        }
+        cc.set_limits();
+        let include: Vec<Vec<usize>> = self
+            .include
+            .iter()
+            .map(|w| trie.translator.translate(w).unwrap())
+            .collect();
+        let include_weights = include.iter().map(|w| trie.arena.weight(w)).collect();
+        let all_words = trie.optimize(&cc, 1, longest, &forbidden);
+        let root = all_words.optimize(&cc, shortest, None, &[]);
+        // note which words satisfy which requirements
+        let mut requirements: Vec<Requirement> = required
+            .iter()
+            .map(|&(_, count)| Requirement {
+                count,
+                shortest: usize::MAX,
+            })
+            .collect();
+        let mut matches = HashMap::new();
+        if !required.is_empty() {
+            for (word, _) in root.words_for(Arc::new(cc.clone()), &[], &true) {
+                let text = root.translator.etalsnart(&word).unwrap();
+                let hits: Vec<usize> = (0..required.len())
+                    .filter(|&i| required[i].0.is_match(&text))
+                    .collect();
+                for &i in &hits {
+                    requirements[i].shortest = cmp::min(requirements[i].shortest, word.len());
+                }
+                if !hits.is_empty() {
+                    matches.insert((*word).clone(), hits);
+                }
+            }
+        }
+        let included_matches = required
+            .iter()
+            .map(|(re, _)| {
+                self.include
+                    .iter()
+                    .filter(|w| re.is_match(&normalize(w)))
+                    .count()
+            })
+            .collect();
+        let shape = Shape {
+            included: include.len(),
+            minimum_words: self.minimum_words,
+            maximum_words: self.maximum_words.unwrap_or(usize::MAX),
+            shortest,
+            longest: longest.unwrap_or(usize::MAX),
+        };
+        // pruning only knows whether residuals can be used up, not how
+        let prunable = self.prune
+            && template.is_none()
+            && self.minimum_words <= 1
+            && self.maximum_words.is_none()
+            && required.is_empty()
+            && leftover == 0;
+        let mut restored = 0;
+        if let Some(ref mut memo) = memo {
+            restored = memo
+                .load(&cc, &root)
+                .map_err(|source| RanaError::UnreadableFile {
+                    path: self.memo_file.clone().unwrap(),
+                    source,
+                })?;
+        }
+        Ok(Anagrammer {
+            fun: Arc::new(AnagramFun {
+                root: Arc::new(root),
+                shape,
+                template,
+                requirements,
+                matches: Arc::new(matches),
+                included_matches,
+                leftover: 0,
+                all_words: leftover > 0,
+                floor: None,
+                strategy: self.strategy,
+                pruner: if prunable {
+                    Some(Arc::new(Pruner::new()))
+                } else {
+                    None
+                },
+            }),
+            leftover,
+            all_words,
+            phrase,
+            cc,
+            include,
+            include_weights,
+            threads: self.threads,
+            seed: self.seed,
+            minimum_word_length: self.minimum_word_length,
+            memo,
+            restored,
+        })
+    }
+    fn make_trie(&self) -> Result<Trie, RanaError> {
+        let (translator, arena) = match self.dictionary {
+            None => return Err(RanaError::invalid("dictionary", "none provided")),
+            Some(Dictionary::Words(ref words)) => {
+                dictionary::parse_ordered(&words.join("\n"), &self.letter_order)
+                    .map_err(|e| RanaError::invalid("dictionary", &e.to_string()))?
+            }
+            Some(Dictionary::File(ref path)) => dictionary::load_ordered(path, &self.letter_order)
+                .map_err(|source| RanaError::UnreadableDictionary {
+                    path: path.clone(),
+                    source,
+                })?,
+        };
+        let mut trie = Trie::new(arena, translator, self.use_cache, self.shuffle);
+        trie.seed = self.seed;
+        trie.cache = Cache::new(self.cache_memory);
+        for word in &self.exclude {
+            // an excluded word with a character foreign to the dictionary cannot
+            // be in the dictionary, so there is nothing to exclude
+            if let Some(translation) = trie.translator.translate(word) {
+                trie.remove(&translation);
+            }
+        }
+        Ok(trie)
+    }
+}
+
+/// A phrase and a word list ready to be queried for anagrams and the words
+/// that might occur in them.
+pub struct Anagrammer {
+    fun: Arc<AnagramFun>,
+    // like fun.root, but without the minimum word length applied; --strict
+    // and --prove need the short words to complete their anagrams
+    all_words: Trie,
+    // the phrase before the included words were taken from it
+    phrase: CharCount,
+    cc: CharCount,
+    include: Vec<Vec<usize>>,
+    include_weights: Vec<f32>,
+    threads: usize,
+    seed: Option<u64>,
+    minimum_word_length: usize,
+    // the most letters of the phrase an anagram may leave unused
+    leftover: usize,
+    memo: Option<Memo>,
+    // the number of cache entries restored from the memo file
+    restored: usize,
+}
+
+impl Anagrammer {
+    /// Start looking for anagrams. Each anagram begins with the words it
+    /// was required to include. If anagrams may leave letters unused, those
+    /// leaving fewer come first.
+    pub fn anagrams(&self) -> Anagrams {
+        let mut anagrams = Anagrams::new(
+            &self.fun,
+            self.cc.clone(),
+            self.threads,
+            &self.include,
+            &self.include_weights,
+        );
+        anagrams.later = (1..=self.leftover)
+            .rev()
+            .map(|n| Arc::new(self.fun.with_leftover(n)))
+            .collect();
+        anagrams.phrase = self.phrase.clone();
+        anagrams
+    }
+    /// The `k` best anagrams by the score the combiner gives their words'
+    /// weights, best first. Rather than finding every anagram, this searches
+    /// the most promising partial anagrams first, and stops once the best
+    /// are known. Among anagrams with equal scores, which are found first is
+    /// arbitrary. Only anagrams using every letter are considered.
+    pub fn top(&self, k: usize, combiner: Combiner) -> Anagrams {
+        let root = &self.fun.root;
+        let best = match root.arena.weights() {
+            Some(weights) => weights.iter().cloned().fold(f32::NEG_INFINITY, f32::max),
+            None => DEFAULT_WEIGHT,
+        };
+        let todo = ToDo::seed(self.cc.clone());
+        let fits = self.fun.fits(&todo, &self.fun.included_matches);
+        let seed = Candidate {
+            todo,
+            tally: self
+                .include_weights
+                .iter()
+                .fold(Tally::new(), |tally, &w| tally.add(w)),
+            best,
+            shortest: self.minimum_word_length.max(1),
+        };
+        let materials = if fits { vec![seed] } else { vec![] };
+        let fun = Arc::new(BestFun {
+            fun: self.fun.clone(),
+            combiner,
+        });
+        let (messages, kill_switch) =
+            factory::manufacture_best_first(self.threads, materials, fun, Some(k))
+                .expect("the thread count was checked by the builder");
+        Anagrams {
+            messages: Messages::Ranked(messages),
+            kill_switch,
+            fun: self.fun.clone(),
+            later: vec![],
+            phrase: self.phrase.clone(),
+            cc: self.cc.clone(),
+            threads: self.threads,
+            include: self.include.clone(),
+            include_weights: self.include_weights.clone(),
+            finished: false,
+        }
+    }
+    /// All the anagrams, those leaving the fewest letters unused first and
+    /// then best first by the score the combiner gives their words' weights.
+    /// Anagrams otherwise equal are in the order of their words.
+    pub fn anagrams_by_score(&self, combiner: Combiner) -> Vec<Anagram> {
+        let mut scored: Vec<(f64, Anagram)> = self
+            .anagrams()
+            .map(|anagram| (anagram.score(combiner), anagram))
+            .collect();
+        scored.sort_by(|(a, x), (b, y)| {
+            x.leftover
+                .len()
+                .cmp(&y.leftover.len())
+                .then_with(|| b.partial_cmp(a).unwrap_or(cmp::Ordering::Equal))
+                .then_with(|| x.words.cmp(&y.words))
+        });
+        scored.into_iter().map(|(_, anagram)| anagram).collect()
+    }
+    /// All the anagrams in the given order, the words found in each listed
+    /// in alphabetical order after the included words. In lexicographic
+    /// order, the anagrams are found one first word at a time, so only
+    /// those sharing a first word are held at once; in other orders, all
+    /// the anagrams are found before any is listed. Either way, each search
+    /// uses every thread. This cannot be combined with a pattern.
+    pub fn sorted(&self, order: Order) -> Result<Sorted, RanaError> {
+        if self.fun.template.is_some() {
+            return Err(RanaError::invalid(
+                "sorted",
+                "cannot be combined with a pattern",
+            ));
+        }
+        let root = &self.fun.root;
+        let mut words: Vec<(String, Vec<usize>)> = root
+            .words_for(Arc::new(self.cc.clone()), &[], &true)
+            .into_iter()
+            .map(|(word, _)| (root.translator.etalsnart(&word).unwrap(), (*word).clone()))
+            .collect();
+        words.sort();
+        let ranks = words
+            .into_iter()
+            .enumerate()
+            .map(|(rank, (_, word))| (word, rank))
+            .collect();
+        let mut sorted = Sorted {
+            // a residual dead above a floor may not be dead below it, so
+            // the floored searches must not tell other searches of it
+            fun: Arc::new(self.fun.with_own_pruner()),
+            later: (1..=self.leftover)
+                .rev()
+                .map(|n| Arc::new(self.fun.with_leftover(n)))
+                .collect(),
+            ranks: Arc::new(ranks),
+            partitions: vec![],
+            held: vec![],
+            phrase: self.phrase.clone(),
+            cc: self.cc.clone(),
+            threads: self.threads,
+            include: self.include.clone(),
+            include_weights: self.include_weights.clone(),
+        };
+        sorted.start();
+        if order != Order::Lexicographic {
+            let mut all: Vec<Anagram> = sorted.by_ref().collect();
+            // the anagrams are held last first
+            all.sort_by(|a, b| order.compare(b, a));
+            sorted.held = all;
+        }
+        Ok(sorted)
+    }
+    /// A uniformly random sample of `n` distinct anagrams, in random order,
+    /// or all the anagrams if there are no more than `n`. Rather than finding
+    /// every anagram, this counts the anagrams of each residual met and picks
+    /// anagrams by their ranks among the rest. If the builder was given a
+    /// seed, the sample is chosen with it. This cannot be combined with
+    /// limits on the number of words, a pattern, required matches, or
+    /// leftovers.
+    pub fn sample(&self, n: usize) -> Result<Vec<Anagram>, RanaError> {
+        self.countable("sample")?;
+        let mut counter = Counter::new(&self.fun.root, self.fun.all_words);
+        let cc = Arc::new(self.cc.clone());
+        let total = self.total(&mut counter, &cc);
+        let empty = self.fun.root.translator.count("").unwrap();
+        let mut rng = match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_rng(thread_rng()).unwrap(),
+        };
+        Ok(count::ranks(&total, n, &mut rng)
+            .into_iter()
+            .map(|rank| {
+                let found = counter.unrank(&cc, rank);
+                assemble(
+                    &self.fun,
+                    &self.phrase,
+                    &self.include,
+                    &self.include_weights,
+                    found,
+                    &empty,
+                )
+            })
+            .collect())
+    }
+    /// The exact number of anagrams, found without finding the anagrams
+    /// themselves: each residual met is counted only once, however many
+    /// partial anagrams leave it. This cannot be combined with limits on the
+    /// number of words, a pattern, required matches, or leftovers.
+    pub fn count(&self) -> Result<Count, RanaError> {
+        self.countable("count")?;
+        let mut counter = Counter::new(&self.fun.root, self.fun.all_words);
+        Ok(self.total(&mut counter, &Arc::new(self.cc.clone())))
+    }
+    // whether the anagrams can be counted, which they can't if more than the
+    // residual letters and the last word matter to the anagrams they may
+    // complete
+    fn countable(&self, what: &str) -> Result<(), RanaError> {
+        let shape = &self.fun.shape;
+        if shape.minimum_words > 1
+            || shape.maximum_words < usize::MAX
+            || self.fun.template.is_some()
+            || !self.fun.requirements.is_empty()
+            || self.leftover > 0
+        {
+            return Err(RanaError::invalid(
+                what,
+                "cannot be combined with word counts, patterns, required matches, or leftovers",
+            ));
+        }
+        Ok(())
+    }
+    // the number of anagrams of the phrase; a phrase used up by the included
+    // words has one if there are any
+    fn total(&self, counter: &mut Counter, cc: &Arc<CharCount>) -> Count {
+        if cc.is_empty() && self.include.is_empty() {
+            Count::zero()
+        } else {
+            counter.count(cc, &[])
+        }
+    }
+    /// The contents and history of the cache used in finding anagrams.
+    pub fn cache_stats(&self) -> CacheStats {
+        self.fun.root.cache.stats()
+    }
+    /// How many residuals were found never to complete, and how much of the
+    /// search this saved.
+    pub fn prune_stats(&self) -> PruneStats {
+        match self.fun.pruner {
+            Some(ref pruner) => pruner.stats(),
+            None => PruneStats::default(),
+        }
+    }
+    /// The number of cache entries restored from the memo file.
+    pub fn restored(&self) -> usize {
+        self.restored
+    }
+    /// Appends the cache entries found since the memo file was read or last
+    /// saved to it, returning how many there were. This does nothing if no
+    /// memo file was given.
+    pub fn save_memo(&mut self) -> Result<usize, RanaError> {
+        match self.memo {
+            Some(ref mut memo) => {
+                memo.save(&self.fun.root)
+                    .map_err(|source| RanaError::UnwritableFile {
+                        path: memo.path().to_string(),
+                        source,
+                    })
+            }
+            None => Ok(0),
+        }
+    }
+    /// The words composable from some subset of the phrase, sorted.
+    pub fn words_in(&self) -> Vec<String> {
+        let sort_key = Vec::with_capacity(0);
+        let trie = &self.fun.root;
+        let mut found: Vec<String> = trie
+            .words_for(Arc::new(self.cc.clone()), &sort_key, &true)
+            .into_iter()
+            .map(|(chars, _)| trie.translator.etalsnart(&chars).unwrap())
+            .collect();
+        found.sort();
+        found
+    }
+    /// The words composable from the phrase which occur in some anagram,
+    /// sorted.
+    pub fn strict_words_in(&self) -> Vec<String> {
+        self.occurring_words(false)
+            .into_iter()
+            .map(|(word, _)| word)
+            .collect()
+    }
+    /// Like `strict_words_in`, but each word is paired with an anagram
+    /// containing it.
+    pub fn proven_words_in(&self) -> Vec<(String, String)> {
+        self.occurring_words(true)
+            .into_iter()
+            .map(|(word, proof)| (word, proof.unwrap()))
+            .collect()
+    }
+    fn occurring_words(&self, prove: bool) -> Vec<(String, Option<String>)> {
+        let mut trie = self.all_words.clone();
+        trie.use_cache = false;
+        let cc = &self.cc;
+        let min_word_length = self.minimum_word_length;
+        let threads = self.threads;
+        let sort_key = Vec::with_capacity(0);
+        // find candidate words -- these are embedded in the initial character count and thus
+        // are the only ones that might be in an anagram
+        let mut found: Vec<String> = trie
+            .words_for(Arc::new(cc.clone()), &sort_key, &true)
+            .into_iter()
+            .map(|(chars, _)| trie.translator.etalsnart(&chars).unwrap())
+            .collect();
+        found.sort();
+        let mut occurring = vec![];
+        let mut cache: HashMap<String, String> = HashMap::new();
+        for word in found {
+            if word.len() < min_word_length {
+                continue;
+            }
+            if let Some(proof) = cache.get(&word) {
+                occurring.push((word.clone(), if prove { Some(proof.clone()) } else { None }));
+            } else if let Some(usizes) = trie.translator.translate(word.as_str()) {
+                // can we make a least one anagram with the remainder after we subtract this word?
+                let mut cc = cc.clone();
+                cc.subtract(usizes.to_vec());
+                let noah = Arc::new(AnagramFun::new(trie.clone()));
+                let first = Anagrams::new(&noah, cc, threads, &[], &[]).next();
+                if let Some(done) = first {
+                    let s = if prove {
+                        word.clone() + " " + &done.to_string()
+                    } else {
+                        done.to_string()
+                    };
+                    if prove {
+                        occurring.push((word.clone(), Some(s.clone())));
+                    } else {
+                        occurring.push((word.clone(), None));
+                        // only do this if we're not proving, because we aren't always saving the proof in the cache
+                        for ow in words_in_word(&word, &noah, threads) {
+                            if ow.len() >= min_word_length && ow > word && !cache.contains_key(&ow)
+                            {
+                                cache.insert(ow, s.clone());
+                            }
+                        }
+                    }
+                    for other_word in s.as_str().split(' ') {
+                        if prove {
+                            if other_word.len() >= min_word_length && other_word > word.as_str() {
+                                cache.insert(String::from(other_word), s.clone());
+                            }
+                        } else {
+                            for ow in words_in_word(other_word, &noah, threads) {
+                                if ow.len() >= min_word_length
+                                    && ow > word
+                                    && !cache.contains_key(&ow)
+                                {
+                                    cache.insert(ow, s.clone());
+                                }
+                            }
+                        }
+                    }
+                } else {
+                    trie.remove(&usizes);
+                }
+            }
+        }
+        occurring
+    }
+}
+
+// fetch the anagrams of a word -- useful for --strict and --prove
+fn words_in_word(word: &str, noah: &Arc<AnagramFun>, threads: usize) -> Vec<String> {
+    let translator = &noah.root.translator;
+    let mut cc = translator
+        .count(&normalize(""))
+        .expect("no luck with the char count");
+    // the word came out of the trie, so it must be translatable
+    cc.add(translator.translate(word).unwrap());
+    cc.set_limits();
+    Anagrams::new(noah, cc, threads, &[], &[])
+        .flat_map(|anagram| anagram.words)
+        .collect()
+}
+
+/// An anagram found by an `Anagrammer`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Anagram {
+    /// the words of the anagram
+    pub words: Vec<String>,
+    /// the words in their numeric representation
+    pub numbers: Vec<Vec<usize>>,
+    /// the words' weights in the dictionary
+    pub weights: Vec<f32>,
+    /// the letters of the phrase left unused, sorted
+    pub leftover: String,
+}
+
+impl Anagram {
+    /// The anagram's score under the given combiner.
+    pub fn score(&self, combiner: Combiner) -> f64 {
+        combiner.combine(&self.weights)
+    }
+}
+
+impl fmt::Display for Anagram {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.words.join(" "))?;
+        if !self.leftover.is_empty() {
+            write!(f, " [+{}]", self.leftover)?;
+        }
+        Ok(())
+    }
+}
+
+/// A stream of anagrams fed by the worker threads of a factory. Dropping it
+/// stops the workers.
+pub struct Anagrams {
+    messages: Messages,
+    kill_switch: Arc<AtomicBool>,
+    fun: Arc<AnagramFun>,
+    // the searches to make once this one is finished, last first
+    later: Vec<Arc<AnagramFun>>,
+    // the phrase, for telling which letters of the words are blanks
+    phrase: CharCount,
+    cc: CharCount,
+    threads: usize,
+    include: Vec<Vec<usize>>,
+    include_weights: Vec<f32>,
+    finished: bool,
+}
+
+impl Anagrams {
+    fn new(
+        fun: &Arc<AnagramFun>,
+        cc: CharCount,
+        threads: usize,
+        include: &[Vec<usize>],
+        include_weights: &[f32],
+    ) -> Anagrams {
+        let (messages, kill_switch) = Anagrams::search(fun, &cc, threads);
+        Anagrams {
+            messages,
+            kill_switch,
+            fun: fun.clone(),
+            later: vec![],
+            phrase: cc.clone(),
+            cc,
+            threads,
+            include: include.to_vec(),
+            include_weights: include_weights.to_vec(),
+            finished: false,
+        }
+    }
+    // start looking for anagrams of the character count
+    fn search(
+        fun: &Arc<AnagramFun>,
+        cc: &CharCount,
+        threads: usize,
+    ) -> (Messages, Arc<AtomicBool>) {
+        if fun.root.shuffle && fun.root.seed.is_some() {
+            // the anagrams beginning with each first word are searched for
+            // in turn, in the order the seed shuffles the first words into
+            let mut parts: Vec<ToDo> = Anagrams::materials(fun, cc)
+                .into_iter()
+                .flat_map(|seed| {
+                    if fun.inspect(&seed) {
+                        vec![seed]
+                    } else {
+                        fun.improve(seed)
+                    }
+                })
+                .collect();
+            parts.reverse();
+            let messages = Messages::Parts {
+                parts,
+                held: vec![],
+            };
+            return (messages, Arc::new(AtomicBool::new(false)));
+        }
+        let (messages, kill_switch) = Anagrams::manufacture(fun, cc, threads);
+        (Messages::Any(messages), kill_switch)
+    }
+    // start the workers looking for anagrams of the character count
+    fn manufacture(
+        fun: &Arc<AnagramFun>,
+        cc: &CharCount,
+        threads: usize,
+    ) -> (Receiver<Option<ToDo>>, Arc<AtomicBool>) {
+        factory::manufacture(threads, 3, Anagrams::materials(fun, cc), fun.clone())
+            .expect("the thread count was checked by the builder")
+    }
+    // what a search for anagrams of the character count starts from
+    fn materials(fun: &AnagramFun, cc: &CharCount) -> Vec<ToDo> {
+        let seed = ToDo::seed(cc.clone());
+        if fun.fits(&seed, &fun.included_matches) {
+            vec![seed]
+        } else {
+            vec![]
+        }
+    }
+    // all the anagrams a part of a seeded shuffled search becomes, in an
+    // order derived from the seed and the part rather than from the timing
+    // of the threads finding them, last first
+    fn search_part(fun: &Arc<AnagramFun>, part: ToDo, threads: usize, seed: u64) -> Vec<ToDo> {
+        let mut rng = Trie::stream(seed, part.undone(), part.word());
+        let (messages, _) = factory::manufacture(threads, 3, vec![part], fun.clone())
+            .expect("the thread count was checked by the builder");
+        let mut found: Vec<(Vec<Vec<usize>>, ToDo)> = messages
+            .iter()
+            .map_while(|message| message)
+            .map(|todo| (todo.words(), todo))
+            .collect();
+        found.sort_by(|(a, _), (b, _)| a.cmp(b));
+        found.shuffle(&mut rng);
+        found.into_iter().map(|(_, todo)| todo).collect()
+    }
+    // the next message from the workers, or None once they are finished
+    fn receive(&mut self) -> Option<ToDo> {
+        match self.messages {
+            Messages::Any(ref messages) => messages.recv().ok().and_then(|m| m),
+            Messages::Ranked(ref messages) => messages.recv().ok().and_then(|m| m.map(|c| c.todo)),
+            Messages::Parts {
+                ref mut parts,
+                ref mut held,
+            } => loop {
+                if let Some(todo) = held.pop() {
+                    return Some(todo);
+                }
+                let seed = self.fun.root.seed?;
+                *held = Anagrams::search_part(&self.fun, parts.pop()?, self.threads, seed);
+            },
+        }
+    }
+}
+
+impl Iterator for Anagrams {
+    type Item = Anagram;
+    fn next(&mut self) -> Option<Anagram> {
+        if self.finished {
+            return None;
+        }
+        let mut received = self.receive();
+        while received.is_none() {
+            // move on to the anagrams leaving more letters unused
+            let fun = match self.later.pop() {
+                Some(fun) => fun,
+                None => break,
+            };
+            let (messages, kill_switch) = Anagrams::search(&fun, &self.cc, self.threads);
+            self.messages = messages;
+            self.kill_switch = kill_switch;
+            self.fun = fun;
+            received = self.receive();
+        }
+        match received {
+            Some(todo) => {
+                // the words are traced back from the last
+                let mut found = todo.words();
+                found.reverse();
+                Some(assemble(
+                    &self.fun,
+                    &self.phrase,
+                    &self.include,
+                    &self.include_weights,
+                    found,
+                    todo.undone(),
+                ))
+            }
+            None => {
+                self.finished = true;
+                None
+            }
+        }
+    }
+}
+
+// where an `Anagrams` gets its anagrams
+enum Messages {
+    Any(Receiver<Option<ToDo>>),
+    Ranked(Receiver<Option<Candidate>>),
+    // the parts of a seeded shuffled search yet to be made, and the anagrams
+    // of the last made not yet listed, both last first
+    Parts { parts: Vec<ToDo>, held: Vec<ToDo> },
+}
+
+impl Drop for Anagrams {
+    fn drop(&mut self) {
+        self.kill_switch.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Anagrams in some `Order`, found by `Anagrammer::sorted`.
+pub struct Sorted {
+    fun: Arc<AnagramFun>,
+    // the searches to make once this one is finished, last first
+    later: Vec<Arc<AnagramFun>>,
+    // the alphabetical rank of every word the phrase contains
+    ranks: Arc<HashMap<Vec<usize>, usize>>,
+    // the first words of the anagrams yet to be searched for, with what
+    // they leave, last first; as they are searched for in alphabetical order,
+    // a residual the pruner finds dead with one first word's floor is dead
+    // with every later one's, so these searches share a pruner, though not
+    // with any search outside this `Sorted`
+    partitions: Vec<(Arc<Vec<usize>>, Arc<CharCount>)>,
+    // the anagrams found but not yet listed, last first
+    held: Vec<Anagram>,
+    phrase: CharCount,
+    cc: CharCount,
+    threads: usize,
+    include: Vec<Vec<usize>>,
+    include_weights: Vec<f32>,
+}
+
+impl Sorted {
+    // begin the current search by finding the words anagrams may begin with
+    fn start(&mut self) {
+        let fun = &self.fun;
+        let seed = Arc::new(ToDo::seed(self.cc.clone()));
+        if !fun.fits(&seed, &fun.included_matches) {
+            return;
+        }
+        if fun.inspect(&seed) {
+            // the included words are the only anagram
+            self.held.push(self.assemble(vec![], seed.undone()));
+            return;
+        }
+        let extensions = fun
+            .root
+            .extensions_for(seed.undone().clone(), &[], &fun.all_words);
+        let mut firsts: Vec<(Arc<Vec<usize>>, Arc<CharCount>)> = extensions
+            .offsets
+            .iter()
+            .filter(|&&offset| {
+                let todo = ToDo::new(seed.clone(), extensions.list.clone(), offset);
+                fun.fits(&todo, &fun.included_matches)
+            })
+            .map(|&offset| extensions.list[offset as usize].clone())
+            .collect();
+        firsts.sort_by_key(|(word, _)| cmp::Reverse(self.ranks[&word[..]]));
+        self.partitions = firsts;
+    }
+    // find all the anagrams beginning with the word, whose other words all
+    // sort alphabetically at or after it, and hold them in order
+    fn search(&mut self, word: Arc<Vec<usize>>, residual: Arc<CharCount>) {
+        let fun = Arc::new(self.fun.after(
+            &word,
+            Floor {
+                ranks: self.ranks.clone(),
+                rank: self.ranks[&word[..]],
+            },
+        ));
+        let (messages, _) = Anagrams::manufacture(&fun, &residual, self.threads);
+        let mut held: Vec<Anagram> = messages
+            .iter()
+            .map_while(|message| message)
+            .map(|todo| {
+                let mut found = todo.words();
+                found.push((*word).clone());
+                // assembly reverses the words found
+                found.sort_by_key(|w| cmp::Reverse(self.ranks[w]));
+                self.assemble(found, todo.undone())
+            })
+            .collect();
+        held.sort_by(|a, b| Order::Lexicographic.compare(b, a));
+        self.held = held;
+    }
+    fn assemble(&self, found: Vec<Vec<usize>>, undone: &CharCount) -> Anagram {
+        assemble(
+            &self.fun,
+            &self.phrase,
+            &self.include,
+            &self.include_weights,
+            found,
+            undone,
+        )
+    }
+}
+
+impl Iterator for Sorted {
+    type Item = Anagram;
+    fn next(&mut self) -> Option<Anagram> {
+        loop {
+            if let Some(anagram) = self.held.pop() {
+                return Some(anagram);
+            }
+            if let Some((word, residual)) = self.partitions.pop() {
+                self.search(word, residual);
+                continue;
+            }
+            // move on to the anagrams leaving more letters unused
+            self.fun = self.later.pop()?;
+            self.start();
+        }
+    }
+}
+
+// make an anagram of the included words and the words found, in the order
+// they were found, with what they leave
+// repeat_n is too new for the compilers rana still builds with
+#[allow(unknown_lints, clippy::manual_repeat_n)]
+fn assemble(
+    fun: &AnagramFun,
+    phrase: &CharCount,
+    include: &[Vec<usize>],
+    include_weights: &[f32],
+    found: Vec<Vec<usize>>,
+    undone: &CharCount,
+) -> Anagram {
+    let arena = &fun.root.arena;
+    let mut weights = include_weights.to_vec();
+    weights.extend(found.iter().map(|w| arena.weight(w)));
+    let mut numbers = include.to_vec();
+    numbers.extend(found);
+    let mut words = spell(&fun.root.translator, phrase, &numbers);
+    if fun.template.is_none() {
+        // list the found words last first, as they are traced
+        let n = include.len();
+        numbers[n..].reverse();
+        weights[n..].reverse();
+        words[n..].reverse();
+    }
+    let mut leftover: Vec<char> = fun
+        .root
+        .translator
+        .etalsnart(&undone.letters())
+        .unwrap()
+        .chars()
+        .collect();
+    leftover.sort_unstable();
+    leftover.extend(std::iter::repeat(BLANK).take(undone.blanks));
+    Anagram {
+        words,
+        numbers,
+        weights,
+        leftover: leftover.into_iter().collect(),
+    }
+}
+
+// the words, in the order they were taken from the phrase, spelled out with
+// the letters blanks stand for in uppercase; as in the search, each word uses
+// blanks only for the letters the phrase has run out of
+fn spell(translator: &Translator, phrase: &CharCount, numbers: &[Vec<usize>]) -> Vec<String> {
+    let mut left = phrase.clone();
+    numbers
+        .iter()
+        .map(|w| {
+            let word = translator.etalsnart(w).unwrap();
+            if phrase.blanks == 0 {
+                return word;
+            }
+            let mut spelled = String::with_capacity(word.len());
+            for (c, &i) in word.chars().zip(w.iter()) {
+                if left.take(i) == Some(true) {
+                    spelled.extend(c.to_uppercase());
+                } else {
+                    spelled.push(c);
+                }
+            }
+            spelled
+        })
+        .collect()
+}
+
+fn dictionary_error(word: &str, trie: &Trie) -> RanaError {
+    let (good, bad) = trie.translator.unfamiliar_character(word);
+    RanaError::UnknownCharacter {
+        word: word.to_string(),
+        good,
+        bad,
+    }
+}
+
+#[derive(Clone)]
+struct AnagramFun {
+    root: Arc<Trie>,
+    shape: Shape,
+    template: Option<Template>,
+    requirements: Vec<Requirement>,
+    // the indices of the requirements each word satisfies, for words
+    // satisfying any
+    matches: Arc<HashMap<Vec<usize>, Vec<usize>>>,
+    // how many included words satisfy each requirement
+    included_matches: Vec<usize>,
+    // exactly how many letters anagrams leave unused
+    leftover: usize,
+    // whether to keep extending residuals with letters no word can use,
+    // which anagrams leaving letters unused may do
+    all_words: bool,
+    // if the anagrams sought all begin with some word, the word, which no
+    // word following it may sort alphabetically before
+    floor: Option<Floor>,
+    strategy: Strategy,
+    // the record of residuals which cannot be used up, if the anagrams need
+    // do no more than use them up
+    pruner: Option<Arc<Pruner>>,
+}
+
+// a word in its alphabetical rank among all the words the phrase contains
+#[derive(Clone)]
+struct Floor {
+    ranks: Arc<HashMap<Vec<usize>, usize>>,
+    rank: usize,
+}
+
+// a number of words matching some pattern which every anagram must have
+#[derive(Clone)]
+struct Requirement {
+    count: usize,
+    // the length of the shortest word matching the pattern
+    shortest: usize,
+}
+
+// limits on the number and lengths of the words in an anagram
+#[derive(Debug, Clone, Copy)]
+struct Shape {
+    // the number of words included in every anagram, which count toward the
+    // limits but are not in the ToDos
+    included: usize,
+    minimum_words: usize,
+    maximum_words: usize,
+    shortest: usize,
+    longest: usize,
+}
+
+impl Shape {
+    fn any() -> Shape {
+        Shape {
+            included: 0,
+            minimum_words: 0,
+            maximum_words: usize::MAX,
+            shortest: 1,
+            longest: usize::MAX,
+        }
+    }
+}
+
+impl AnagramFun {
+    fn new(root: Trie) -> AnagramFun {
+        AnagramFun {
+            root: Arc::new(root),
+            shape: Shape::any(),
+            template: None,
+            requirements: vec![],
+            matches: Arc::new(HashMap::new()),
+            included_matches: vec![],
+            leftover: 0,
+            all_words: false,
+            floor: None,
+            strategy: Strategy::SortKey,
+            pruner: None,
+        }
+    }
+    // the same search for anagrams leaving this many letters unused
+    fn with_leftover(&self, leftover: usize) -> AnagramFun {
+        AnagramFun {
+            leftover,
+            pruner: None,
+            ..self.clone()
+        }
+    }
+    // the same search, recording the residuals it finds dead apart from
+    // every other search's
+    fn with_own_pruner(&self) -> AnagramFun {
+        AnagramFun {
+            pruner: self.pruner.as_ref().map(|_| Arc::new(Pruner::new())),
+            ..self.clone()
+        }
+    }
+    // the same search for the rest of the anagrams beginning with a word,
+    // with only words at or after the floor following it
+    fn after(&self, word: &[usize], floor: Floor) -> AnagramFun {
+        let mut included_matches = self.included_matches.clone();
+        if let Some(hits) = self.matches.get(word) {
+            for &i in hits {
+                included_matches[i] += 1;
+            }
+        }
+        AnagramFun {
+            shape: Shape {
+                included: self.shape.included + 1,
+                ..self.shape
+            },
+            included_matches,
+            floor: Some(floor),
+            ..self.clone()
+        }
+    }
+    // the letters the ToDo has left to make words of, if it has enough to
+    // spare the leftovers
+    fn usable(&self, todo: &ToDo) -> Option<usize> {
+        todo.undone().sum.checked_sub(self.leftover)
+    }
+    // how many words satisfying each requirement the ToDo has, counting
+    // included words
+    fn matched(&self, todo: &ToDo) -> Vec<usize> {
+        let mut matched = self.included_matches.clone();
+        if !self.requirements.is_empty() {
+            for word in todo.found() {
+                if let Some(hits) = self.matches.get(word) {
+                    for &i in hits {
+                        matched[i] += 1;
+                    }
+                }
+            }
+        }
+        matched
+    }
+    // whether the letters the ToDo may still use could make the words it
+    // still needs to meet the requirements, given how many its parent had
+    fn satisfiable(&self, todo: &ToDo, before: &[usize], left: usize) -> bool {
+        let hits = self.matches.get(&todo.word()[..]);
+        self.requirements.iter().enumerate().all(|(i, r)| {
+            let have = before[i] + hits.map_or(0, |h| h.contains(&i) as usize);
+            let need = r.count.saturating_sub(have);
+            need == 0 || need.saturating_mul(r.shortest) <= left
+        })
+    }
+    // the words which may extend the ToDo, with what they leave
+    fn extensions(&self, todo: &ToDo) -> Extensions {
+        match self.template {
+            Some(ref template) => {
+                // words come in template order rather than sorted, so any
+                // word fitting the next slot will do
+                let slot = match template.slots().get(todo.depth) {
+                    Some(slot) => *slot,
+                    None => return Extensions::default(),
+                };
+                let mut words =
+                    self.root
+                        .extensions_for(todo.undone().clone(), &[], &self.all_words);
+                words.retain(|word| slot.fits(word.len()));
+                words
+            }
+            None => {
+                let key = self.key(todo);
+                let mut words = match self.strategy {
+                    Strategy::SortKey => {
+                        self.root
+                            .extensions_for(todo.undone().clone(), key, &self.all_words)
+                    }
+                    Strategy::RarestLetter => self.root.words_containing(
+                        todo.undone().clone(),
+                        todo.undone().last - 1,
+                        key,
+                        &self.all_words,
+                    ),
+                };
+                if let Some(ref floor) = self.floor {
+                    words.retain(|word| floor.ranks.get(word).is_some_and(|&r| r >= floor.rank));
+                }
+                words
+            }
+        }
+    }
+    // the word at or after which the words following the ToDo's must sort
+    fn key<'a>(&self, todo: &'a ToDo) -> &'a [usize] {
+        match self.strategy {
+            Strategy::SortKey => todo.word(),
+            Strategy::RarestLetter => {
+                // the translator numbers the rarest letters last; the word
+                // before must be followed in order only if it contained the
+                // rarest letter left too, and so no rarer one
+                let rarest = todo.undone().last - 1;
+                if todo.word().iter().any(|&c| c > rarest) {
+                    &[]
+                } else {
+                    todo.word()
+                }
+            }
+        }
+    }
+    // whether only words sorting at or after a word may follow it
+    fn ordered(&self) -> bool {
+        self.template.is_none() && !self.root.shuffle && self.strategy == Strategy::SortKey
+    }
+    // whether the ToDo might yet become an anagram of the right shape,
+    // given how many words satisfying each requirement its parent had
+    fn fits(&self, todo: &ToDo, before: &[usize]) -> bool {
+        let left = match self.usable(todo) {
+            Some(left) => left,
+            None => return false,
+        };
+        if !self.satisfiable(todo, before, left) {
+            return false;
+        }
+        if let Some(ref template) = self.template {
+            return template.room(todo.depth, left);
+        }
+        let shape = &self.shape;
+        let words = shape.included + todo.depth;
+        if left == 0 {
+            return words >= shape.minimum_words && words <= shape.maximum_words;
+        }
+        // the letters left make at least one more word, and no more words
+        // than there are shortest words in them
+        let most = left / shape.shortest;
+        let least = left.div_ceil(shape.longest);
+        most > 0
+            && words.saturating_add(most) >= shape.minimum_words
+            && words.saturating_add(least) <= shape.maximum_words
+    }
+    // whether the ToDo's residual might be used up, as far as the pruner
+    // knows
+    fn viable(&self, todo: &ToDo) -> bool {
+        match self.pruner {
+            Some(ref pruner) => pruner.viable(todo.undone(), self.key(todo)),
+            None => true,
+        }
+    }
+    // tell the pruner how the children of an improved ToDo turned out: if
+    // none is an anagram or left to work on, the ToDo came to nothing
+    fn settle(&self, todo: &ToDo, working: usize, complete: bool) {
+        if let Some(ref pruner) = self.pruner {
+            if complete {
+                todo.complete();
+            }
+            todo.expect(working);
+            if working == 0 && !complete {
+                todo.abandon(|t| pruner.dead(t.undone(), self.key(t)));
+            }
+        }
+    }
+    // the most words which may be added to the ToDo
+    fn room(&self, todo: &ToDo) -> usize {
+        if let Some(ref template) = self.template {
+            return template.slots().len().saturating_sub(todo.depth);
+        }
+        let shape = &self.shape;
+        let words = shape.included + todo.depth;
+        cmp::min(
+            self.usable(todo).unwrap_or(0) / shape.shortest,
+            shape.maximum_words.saturating_sub(words),
+        )
+    }
+}
+
+impl WorkerFun<ToDo> for AnagramFun {
+    fn improve(&self, needs_work: ToDo) -> Vec<ToDo> {
+        let mut done = vec![];
+        let arc = Arc::new(needs_work);
+        let before = self.matched(&arc);
+        let extensions = self.extensions(&arc);
+        for &offset in &extensions.offsets {
+            let todo = ToDo::new(arc.clone(), extensions.list.clone(), offset);
+            if self.fits(&todo, &before) && self.viable(&todo) {
+                done.push(todo)
+            }
+        }
+        let complete = done.iter().filter(|todo| self.inspect(todo)).count();
+        self.settle(&arc, done.len() - complete, complete > 0);
+        done
+    }
+    fn inspect(&self, thing: &ToDo) -> bool {
+        thing.undone().sum == self.leftover
+    }
+}
+
+// a ToDo with what is needed to bound the scores of the anagrams it may
+// become
+struct Candidate {
+    todo: ToDo,
+    tally: Tally,
+    // the greatest weight and least length of the words which may be added
+    best: f32,
+    shortest: usize,
+}
+
+// AnagramFun for the best-first search
+struct BestFun {
+    fun: Arc<AnagramFun>,
+    combiner: Combiner,
+}
+
+impl WorkerFun<Candidate> for BestFun {
+    fn improve(&self, needs_work: Candidate) -> Vec<Candidate> {
+        let root = &self.fun.root;
+        let arc = Arc::new(needs_work.todo);
+        let extensions = self.fun.extensions(&arc);
+        let before = self.fun.matched(&arc);
+        let weights: Vec<f32> = extensions
+            .iter()
+            .map(|(w, _)| root.arena.weight(w))
+            .collect();
+        // if only words sorting at or after a word can follow it, the
+        // list's suffixes bound what may be added; otherwise any word the
+        // letters allow might be
+        let ordered = self.fun.ordered();
+        let (mut best, mut shortest) = (f32::NEG_INFINITY, usize::MAX);
+        if !ordered {
+            for (word, _) in root.words_for(arc.undone().clone(), &[], &true) {
+                best = best.max(root.arena.weight(&word));
+                shortest = shortest.min(word.len());
+            }
+        }
+        let mut done = Vec::with_capacity(extensions.len());
+        for (i, &offset) in extensions.offsets.iter().enumerate().rev() {
+            if ordered {
+                best = best.max(weights[i]);
+                shortest = shortest.min(extensions.list[offset as usize].0.len());
+            }
+            let todo = ToDo::new(arc.clone(), extensions.list.clone(), offset);
+            if self.fun.fits(&todo, &before) && self.fun.viable(&todo) {
+                done.push(Candidate {
+                    todo,
+                    tally: needs_work.tally.add(weights[i]),
+                    best,
+                    shortest,
+                });
+            }
+        }
+        let complete = done.iter().filter(|c| c.todo.done()).count();
+        self.fun.settle(&arc, done.len() - complete, complete > 0);
+        done
+    }
+    fn inspect(&self, thing: &Candidate) -> bool {
+        thing.todo.done()
+    }
+}
+
+impl RankedWorkerFun<Candidate> for BestFun {
+    fn bound(&self, thing: &Candidate) -> f64 {
+        if thing.todo.done() {
+            thing.tally.score(self.combiner)
+        } else {
+            let most = cmp::min(
+                thing.todo.undone().sum / thing.shortest,
+                self.fun.room(&thing.todo),
+            );
+            thing.tally.bound(self.combiner, thing.best, most)
+        }
+    }
+}
@@ -0,0 +1,264 @@
+//! a memory-bounded store of the words extractable from character counts
+
+use std::collections::VecDeque;
+use std::mem::size_of;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use trie::WordList;
+use util::{CharCount, CharCountMap};
+
+/// The `WordList`s already found for residual `CharCount`s. A `Cache` may be
+/// given a budget in bytes. When an insertion takes it over budget, entries
+/// are evicted by the CLOCK algorithm: entries are examined in the order
+/// of their insertion, and those looked up since they were last examined
+/// get a second chance. Evicted entries are simply recomputed if needed
+/// again, so a full cache slows the search without changing its results.
+///
+/// A cache too small for the search thrashes, evicting entries before they
+/// are ever used. Filling it then costs more than it saves, so the cache
+/// keeps a running estimate of the share of evictions which were wasted, and
+/// while more than a quarter are `worth_filling` advises against most
+/// insertions.
+pub struct Cache {
+    budget: Option<usize>,
+    inner: RwLock<Inner>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+    evictions: AtomicUsize,
+    // the share of recent evictions of never used entries, out of WASTE_SCALE
+    waste: AtomicUsize,
+}
+
+const WASTE_SCALE: usize = 1024;
+// the waste above which the cache is thrashing
+const THRASHING: usize = WASTE_SCALE / 4;
+// how often a thrashing cache is filled anyway to see whether it still is
+const PROBE_INTERVAL: usize = 64;
+
+struct Inner {
+    map: CharCountMap<Entry>,
+    // the keys in the order the clock hand visits them
+    queue: VecDeque<Arc<CharCount>>,
+    bytes: usize,
+}
+
+struct Entry {
+    list: WordList,
+    bytes: usize,
+    referenced: AtomicBool,
+    used: AtomicBool,
+    // whether the entry is in a memo file
+    saved: AtomicBool,
+}
+
+/// A summary of a `Cache`'s contents and use.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub struct CacheStats {
+    /// entries in the cache
+    pub entries: usize,
+    /// the estimated bytes used by these entries
+    pub bytes: usize,
+    /// lookups which found an entry
+    pub hits: usize,
+    /// lookups which found nothing
+    pub misses: usize,
+    /// entries discarded to stay within the budget
+    pub evictions: usize,
+}
+
+impl Cache {
+    /// Makes an empty cache which will hold at most about `budget` bytes, or
+    /// as much as it's given if there is no budget.
+    pub fn new(budget: Option<usize>) -> Cache {
+        Cache {
+            budget,
+            inner: RwLock::new(Inner {
+                map: CharCountMap::default(),
+                queue: VecDeque::new(),
+                bytes: 0,
+            }),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+            evictions: AtomicUsize::new(0),
+            waste: AtomicUsize::new(0),
+        }
+    }
+    /// The maximum number of bytes the cache will hold, if any.
+    pub fn budget(&self) -> Option<usize> {
+        self.budget
+    }
+    /// Looks up the words for the given `CharCount`.
+    pub fn get(&self, cc: &CharCount) -> Option<WordList> {
+        let inner = self.inner.read().unwrap();
+        match inner.map.get(cc) {
+            Some(entry) => {
+                entry.referenced.store(true, Ordering::Relaxed);
+                entry.used.store(true, Ordering::Relaxed);
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Some(entry.list.clone())
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+    /// Whether a missing entry should be computed in full and inserted. This
+    /// is false most of the time when the cache is thrashing.
+    // is_multiple_of is too new for the compilers rana still builds with
+    #[allow(unknown_lints, clippy::manual_is_multiple_of)]
+    pub fn worth_filling(&self) -> bool {
+        self.budget.is_none()
+            || self.waste.load(Ordering::Relaxed) < THRASHING
+            || self.misses.load(Ordering::Relaxed) % PROBE_INTERVAL == 0
+    }
+    /// Stores the words for the given `CharCount`, evicting older entries if
+    /// this exceeds the budget. If another thread got there first, its entry
+    /// is kept.
+    pub fn insert(&self, cc: Arc<CharCount>, list: WordList) {
+        self.store(cc, list, false)
+    }
+    /// Like `insert`, but for entries read from a memo file, which need not
+    /// be saved again.
+    pub fn restore(&self, cc: Arc<CharCount>, list: WordList) {
+        self.store(cc, list, true)
+    }
+    /// The entries which are not yet saved in a memo file. They are marked as
+    /// saved, so they will not be returned again.
+    pub fn take_unsaved(&self) -> Vec<(Arc<CharCount>, WordList)> {
+        let inner = self.inner.read().unwrap();
+        inner
+            .map
+            .iter()
+            .filter(|(_, e)| !e.saved.swap(true, Ordering::Relaxed))
+            .map(|(k, e)| (k.clone(), e.list.clone()))
+            .collect()
+    }
+    fn store(&self, cc: Arc<CharCount>, list: WordList, saved: bool) {
+        let bytes = cost(&cc, &list);
+        let mut inner = self.inner.write().unwrap();
+        if inner.map.contains_key(&cc) {
+            return;
+        }
+        inner.map.insert(
+            cc.clone(),
+            Entry {
+                list,
+                bytes,
+                referenced: AtomicBool::new(false),
+                used: AtomicBool::new(false),
+                saved: AtomicBool::new(saved),
+            },
+        );
+        inner.queue.push_back(cc);
+        inner.bytes += bytes;
+        if let Some(budget) = self.budget {
+            while inner.bytes > budget {
+                let key = match inner.queue.pop_front() {
+                    Some(key) => key,
+                    None => break,
+                };
+                if inner.map[&key].referenced.swap(false, Ordering::Relaxed) {
+                    inner.queue.push_back(key);
+                } else {
+                    let entry = inner.map.remove(&key).unwrap();
+                    inner.bytes -= entry.bytes;
+                    self.evictions.fetch_add(1, Ordering::Relaxed);
+                    // an exponential moving average; only writers update it,
+                    // and they hold the lock
+                    let wasted = if entry.used.load(Ordering::Relaxed) {
+                        0
+                    } else {
+                        WASTE_SCALE
+                    };
+                    let waste = self.waste.load(Ordering::Relaxed);
+                    self.waste
+                        .store(waste - waste / 16 + wasted / 16, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+    /// The current contents and history of the cache.
+    pub fn stats(&self) -> CacheStats {
+        let inner = self.inner.read().unwrap();
+        CacheStats {
+            entries: inner.map.len(),
+            bytes: inner.bytes,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Clone for Cache {
+    fn clone(&self) -> Self {
+        let inner = self.inner.read().unwrap();
+        let map = inner
+            .map
+            .iter()
+            .map(|(k, e)| {
+                let entry = Entry {
+                    list: e.list.clone(),
+                    bytes: e.bytes,
+                    referenced: AtomicBool::new(e.referenced.load(Ordering::Relaxed)),
+                    used: AtomicBool::new(e.used.load(Ordering::Relaxed)),
+                    saved: AtomicBool::new(e.saved.load(Ordering::Relaxed)),
+                };
+                (k.clone(), entry)
+            })
+            .collect();
+        Cache {
+            budget: self.budget,
+            inner: RwLock::new(Inner {
+                map,
+                queue: inner.queue.clone(),
+                bytes: inner.bytes,
+            }),
+            hits: AtomicUsize::new(self.hits.load(Ordering::Relaxed)),
+            misses: AtomicUsize::new(self.misses.load(Ordering::Relaxed)),
+            evictions: AtomicUsize::new(self.evictions.load(Ordering::Relaxed)),
+            waste: AtomicUsize::new(self.waste.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+// an estimate of the bytes an entry holds on to, counting each allocation
+// along with the strong and weak counts of the Arc holding it; the words
+// themselves are shared with the trie, so only the pointers to them count
+fn cost(cc: &CharCount, list: &WordList) -> usize {
+    let arc = 2 * size_of::<usize>();
+    let count = |cc: &CharCount| arc + size_of::<CharCount>() + cc.allocated();
+    let mut bytes = count(cc) + arc + size_of::<Vec<()>>();
+    for (_, residual) in list.iter() {
+        bytes += 2 * size_of::<Arc<()>>();
+        bytes += count(residual);
+    }
+    // the map's and the queue's share of the key, with some slack for the
+    // map's empty slots
+    bytes + 2 * size_of::<Entry>() + 2 * size_of::<Arc<()>>()
+}
+
+/// Parses a size in bytes such as "4096", "512K", "100M", or "2G". The
+/// suffixes are powers of 1024 and may be followed by "B" or "iB".
+pub fn parse_size(size: &str) -> Result<usize, String> {
+    let size = size.trim();
+    let upper = size.to_uppercase();
+    let digits = upper.trim_end_matches(|c: char| c.is_alphabetic());
+    let unit = upper[digits.len()..]
+        .trim_end_matches("IB")
+        .trim_end_matches('B');
+    let multiplier: usize = match unit {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        _ => return Err(format!("unknown unit in {}", size)),
+    };
+    let n = digits
+        .trim()
+        .parse::<usize>()
+        .map_err(|why| why.to_string())?;
+    n.checked_mul(multiplier)
+        .ok_or_else(|| format!("{} is too large", size))
+}
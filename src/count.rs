@@ -0,0 +1,278 @@
+//! counting anagrams exactly without finding them all
+//!
+//! The anagrams of a residual `CharCount` whose words all sort at or after
+//! some word are the anagrams beginning with each such word, followed by
+//! the anagrams of what that word leaves whose words sort at or after it.
+//! The words for a residual are sorted, so a `Counter` keeps for each
+//! residual the sums of these counts over every suffix of its word list.
+//! The count for any lower bound is then one lookup, and each residual is
+//! counted only once however many ways it is reached. The same sums let an
+//! anagram be found directly from its rank among all the anagrams.
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::{Add, Sub};
+use std::sync::Arc;
+use trie::{Trie, WordList};
+use util::{CharCount, CharCountMap};
+
+/// An unsigned integer of any size. The anagrams of a long phrase may be
+/// too many to count in a `u64`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Count {
+    // base 2^32, least significant first, with no leading zeros
+    digits: Vec<u32>,
+}
+
+impl Count {
+    pub fn zero() -> Count {
+        Count { digits: vec![] }
+    }
+    pub fn one() -> Count {
+        Count::from(1)
+    }
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+    /// The count as a `u64`, if it fits.
+    pub fn to_u64(&self) -> Option<u64> {
+        match self.digits.len() {
+            0 => Some(0),
+            1 => Some(u64::from(self.digits[0])),
+            2 => Some(u64::from(self.digits[0]) | u64::from(self.digits[1]) << 32),
+            _ => None,
+        }
+    }
+    /// A number chosen uniformly at random from those less than this one,
+    /// which must be positive.
+    pub fn random_below<R: Rng + ?Sized>(&self, rng: &mut R) -> Count {
+        assert!(!self.is_zero(), "no number is less than zero");
+        let top = *self.digits.last().unwrap();
+        let mask = u32::MAX >> top.leading_zeros();
+        // each try succeeds with a probability of at least one half
+        loop {
+            let mut digits: Vec<u32> = (0..self.digits.len()).map(|_| rng.gen()).collect();
+            *digits.last_mut().unwrap() &= mask;
+            let n = Count::from_digits(digits);
+            if n < *self {
+                return n;
+            }
+        }
+    }
+    fn from_digits(mut digits: Vec<u32>) -> Count {
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        Count { digits }
+    }
+    // divides the count in place, returning the remainder
+    fn divide(&mut self, divisor: u32) -> u32 {
+        let mut remainder = 0u64;
+        for digit in self.digits.iter_mut().rev() {
+            let n = remainder << 32 | u64::from(*digit);
+            *digit = (n / u64::from(divisor)) as u32;
+            remainder = n % u64::from(divisor);
+        }
+        while self.digits.last() == Some(&0) {
+            self.digits.pop();
+        }
+        remainder as u32
+    }
+}
+
+impl From<u64> for Count {
+    fn from(n: u64) -> Count {
+        Count::from_digits(vec![n as u32, (n >> 32) as u32])
+    }
+}
+
+impl Add for &Count {
+    type Output = Count;
+    fn add(self, other: &Count) -> Count {
+        let length = self.digits.len().max(other.digits.len());
+        let mut digits = Vec::with_capacity(length + 1);
+        let mut carry = 0u64;
+        for i in 0..length {
+            let a = u64::from(self.digits.get(i).cloned().unwrap_or(0));
+            let b = u64::from(other.digits.get(i).cloned().unwrap_or(0));
+            let sum = a + b + carry;
+            digits.push(sum as u32);
+            carry = sum >> 32;
+        }
+        digits.push(carry as u32);
+        Count::from_digits(digits)
+    }
+}
+
+impl Sub for &Count {
+    type Output = Count;
+    /// Panics if the difference would be negative.
+    fn sub(self, other: &Count) -> Count {
+        assert!(*self >= *other, "subtraction underflow");
+        let mut digits = Vec::with_capacity(self.digits.len());
+        let mut borrow = 0i64;
+        for (i, &a) in self.digits.iter().enumerate() {
+            let b = i64::from(other.digits.get(i).cloned().unwrap_or(0));
+            let mut difference = i64::from(a) - b - borrow;
+            borrow = 0;
+            if difference < 0 {
+                difference += 1 << 32;
+                borrow = 1;
+            }
+            digits.push(difference as u32);
+        }
+        Count::from_digits(digits)
+    }
+}
+
+impl Ord for Count {
+    fn cmp(&self, other: &Count) -> Ordering {
+        self.digits
+            .len()
+            .cmp(&other.digits.len())
+            .then_with(|| self.digits.iter().rev().cmp(other.digits.iter().rev()))
+    }
+}
+
+impl PartialOrd for Count {
+    fn partial_cmp(&self, other: &Count) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Count {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+        // peel off nine decimal digits at a time
+        let mut n = self.clone();
+        let mut chunks = vec![];
+        while !n.is_zero() {
+            chunks.push(n.divide(1_000_000_000));
+        }
+        write!(f, "{}", chunks.pop().unwrap())?;
+        for chunk in chunks.iter().rev() {
+            write!(f, "{:09}", chunk)?;
+        }
+        Ok(())
+    }
+}
+
+/// Chooses `n` distinct numbers uniformly at random from those less than
+/// the total, in random order. If the total is no more than `n`, these are
+/// all of them.
+pub fn ranks<R: Rng + ?Sized>(total: &Count, n: usize, rng: &mut R) -> Vec<Count> {
+    if let Some(t) = total.to_u64() {
+        if t <= 2 * n as u64 {
+            // few enough to shuffle outright
+            let mut all: Vec<u64> = (0..t).collect();
+            all.shuffle(rng);
+            all.truncate(n);
+            return all.into_iter().map(Count::from).collect();
+        }
+    }
+    // at least half the numbers remain unchosen, so collisions are rare
+    let mut chosen = HashSet::with_capacity(n);
+    let mut ranks = Vec::with_capacity(n);
+    while ranks.len() < n {
+        let rank = total.random_below(rng);
+        if chosen.insert(rank.clone()) {
+            ranks.push(rank);
+        }
+    }
+    ranks
+}
+
+// the words for a residual and the number of anagrams beginning with each
+// word or any after it
+struct Completions {
+    list: WordList,
+    // one longer than the list, ending in zero
+    suffixes: Vec<Count>,
+}
+
+impl Completions {
+    // the offset of the first word sorting at or after the key
+    fn start(&self, key: &[usize]) -> usize {
+        self.list.partition_point(|(word, _)| word[..] < *key)
+    }
+}
+
+/// Counts the anagrams of residual `CharCount`s, remembering the counts of
+/// every residual it meets. The anagrams are those the trie's words make,
+/// each multiset of words counted once.
+pub struct Counter<'a> {
+    trie: &'a Trie,
+    all_words: bool,
+    memo: CharCountMap<Arc<Completions>>,
+}
+
+impl<'a> Counter<'a> {
+    /// Counts the anagrams made of the trie's words. `all_words` is passed
+    /// on to `Trie::sorted_words_for`.
+    pub fn new(trie: &'a Trie, all_words: bool) -> Counter<'a> {
+        Counter {
+            trie,
+            all_words,
+            memo: CharCountMap::default(),
+        }
+    }
+    /// The number of anagrams of the `CharCount` whose words all sort at or
+    /// after the key. An empty `CharCount` has one anagram, of no words.
+    pub fn count(&mut self, cc: &Arc<CharCount>, key: &[usize]) -> Count {
+        if cc.is_empty() {
+            return Count::one();
+        }
+        let completions = self.completions(cc);
+        completions.suffixes[completions.start(key)].clone()
+    }
+    /// The anagram of the given rank among those of the `CharCount`, which
+    /// must be less than their count. The anagrams are ranked in the order
+    /// of their words, which are listed in sorted order.
+    pub fn unrank(&mut self, cc: &Arc<CharCount>, mut rank: Count) -> Vec<Vec<usize>> {
+        let mut words = vec![];
+        let mut cc = cc.clone();
+        let mut key: Arc<Vec<usize>> = Arc::new(vec![]);
+        while !cc.is_empty() {
+            let completions = self.completions(&cc);
+            let start = completions.start(&key);
+            let suffixes = &completions.suffixes[start..];
+            // the rank counted back from the end of the suffix, at least 1
+            let target = &suffixes[0] - &rank;
+            assert!(!target.is_zero(), "rank out of range");
+            // the last word whose suffix reaches the target
+            let i = suffixes.partition_point(|s| *s >= target) - 1;
+            rank = &suffixes[i] - &target;
+            let (ref word, ref residual) = completions.list[start + i];
+            words.push((**word).clone());
+            key = word.clone();
+            cc = residual.clone();
+        }
+        words
+    }
+    /// The number of distinct residuals counted.
+    pub fn residuals(&self) -> usize {
+        self.memo.len()
+    }
+    fn completions(&mut self, cc: &Arc<CharCount>) -> Arc<Completions> {
+        if let Some(completions) = self.memo.get(cc) {
+            return completions.clone();
+        }
+        let list = self.trie.sorted_words_for(cc, &self.all_words);
+        let counts: Vec<Count> = list
+            .iter()
+            .map(|(word, residual)| self.count(residual, word))
+            .collect();
+        let mut suffixes = vec![Count::zero(); list.len() + 1];
+        for i in (0..list.len()).rev() {
+            suffixes[i] = &suffixes[i + 1] + &counts[i];
+        }
+        let completions = Arc::new(Completions { list, suffixes });
+        self.memo.insert(cc.clone(), completions.clone());
+        completions
+    }
+}
@@ -0,0 +1,383 @@
+//! reading word lists and their precompiled binary form
+//!
+//! A compiled dictionary saves rebuilding the `Translator` and the trie from
+//! a text word list on every run. Its layout, all integers little-endian, is
+//!
+//! ```text
+//! magic     b"rana"
+//! version   u32
+//! checksum  u64, FNV-1a over everything after the header
+//! alphabet  u32 count followed by that many u32 code points in numeric order
+//! nodes     u32 count followed by that many nodes, where a node is
+//!             u8 terminal flag
+//!             u32 child count
+//!             that many pairs of u32 child index and u32 node number
+//! weights   u32 count followed by that many f32 weights
+//! ```
+//!
+//! Nodes are numbered in the order listed. Children precede their parents, so
+//! the last node is the root of the trie. Because they are shared, the nodes
+//! form a DAWG rather than a tree. This is the layout of a `TrieArena`, so a
+//! compiled dictionary is read directly into one. An unweighted dictionary
+//! has no weights; a weighted one has one per word, in sorted order.
+//!
+//! A word list may give its words weights, such as their frequencies, by
+//! following a word with a tab and a number.
+//!
+//! A compiled dictionary numbers its letters by frequency. A dictionary
+//! loaded with another `LetterOrder` is renumbered as it is read.
+
+use letter_order::LetterOrder;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use trie::{Edge, Node, TrieArena, TrieArenaBuilder};
+use util::{normalize, Translator};
+
+const MAGIC: &[u8; 4] = b"rana";
+const VERSION: u32 = 3;
+const HEADER_LENGTH: usize = 16;
+
+/// The source of the words an `Anagrammer` may use in anagrams.
+#[derive(Debug, Clone)]
+pub enum Dictionary {
+    /// A line-delimited list of words in a file, or a compiled dictionary.
+    File(String),
+    /// A list of words already in memory.
+    Words(Vec<String>),
+}
+
+/// Builds the `Translator` and trie for a line-delimited word list. A word
+/// may be followed by a tab and its weight. This fails if some weight is not
+/// a number.
+pub fn parse(text: &str) -> io::Result<(Translator, TrieArena)> {
+    let mut words = vec![];
+    for (i, line) in text.lines().enumerate() {
+        let mut parts = line.splitn(2, '\t');
+        let word = parts.next().unwrap();
+        if word.trim().is_empty() {
+            continue;
+        }
+        let weight = match parts.next().map(str::trim) {
+            Some(weight) if !weight.is_empty() => match weight.parse::<f32>() {
+                Ok(weight) if weight.is_finite() => Some(weight),
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("bad weight on line {}: {}", i + 1, weight),
+                    ))
+                }
+            },
+            _ => None,
+        };
+        words.push((word, weight));
+    }
+    let translator = Translator::new(normalize, words.iter().map(|&(w, _)| w));
+    let mut t = TrieArenaBuilder::new();
+    for (word, weight) in words {
+        let translation = translator.translate(word).unwrap();
+        if !translation.is_empty() {
+            match weight {
+                Some(weight) => t.add_weighted(&translation, weight),
+                None => t.add(&translation),
+            }
+        }
+    }
+    Ok((translator, t.build()))
+}
+
+/// The file a compiled form of the given word list is kept in.
+pub fn compiled_path(path: &str) -> String {
+    format!("{}.rana", path)
+}
+
+/// Reads a dictionary file. This may be either a compiled dictionary or a
+/// word list. If it is a word list and there is a compiled version of it at
+/// `compiled_path` at least as new as the word list, the compiled version
+/// is used instead. If the compiled version is older, it is rebuilt.
+pub fn load(path: &str) -> io::Result<(Translator, TrieArena)> {
+    let bytes = read(path)?;
+    if bytes.starts_with(MAGIC) {
+        return decode(&bytes);
+    }
+    let compiled = compiled_path(path);
+    if let (Ok(source), Ok(target)) = (fs::metadata(path), fs::metadata(&compiled)) {
+        if let (Ok(source), Ok(target)) = (source.modified(), target.modified()) {
+            if target >= source {
+                if let Ok(decoded) = read(&compiled).and_then(|b| decode(&b)) {
+                    return Ok(decoded);
+                }
+            }
+        }
+        // the compiled dictionary is stale or damaged
+        let (translator, arena) = parse(&text(bytes)?)?;
+        // failure to write the compiled file costs nothing but speed
+        write(&compiled, &translator, &arena).ok();
+        return Ok((translator, arena));
+    }
+    parse(&text(bytes)?)
+}
+
+/// Like `parse`, but numbering the letters in the given order.
+pub fn parse_ordered(text: &str, order: &LetterOrder) -> io::Result<(Translator, TrieArena)> {
+    let (translator, arena) = parse(text)?;
+    Ok(renumber(translator, arena, order))
+}
+
+/// Like `load`, but numbering the letters in the given order.
+pub fn load_ordered(path: &str, order: &LetterOrder) -> io::Result<(Translator, TrieArena)> {
+    let (translator, arena) = load(path)?;
+    Ok(renumber(translator, arena, order))
+}
+
+// parsed and compiled dictionaries are already in frequency order
+fn renumber(
+    translator: Translator,
+    arena: TrieArena,
+    order: &LetterOrder,
+) -> (Translator, TrieArena) {
+    match *order {
+        LetterOrder::Frequency => (translator, arena),
+        _ => reorder(translator, arena, order),
+    }
+}
+
+/// Renumbers the letters of a dictionary in the given order. Letters are
+/// counted once per word in the dictionary, however often a word was listed.
+pub fn reorder(
+    translator: Translator,
+    arena: TrieArena,
+    order: &LetterOrder,
+) -> (Translator, TrieArena) {
+    let mut words = vec![];
+    gather(&arena, arena.root(), &mut vec![], &mut words);
+    let mut counts = vec![0; translator.alphabet_size()];
+    for word in &words {
+        for &c in word {
+            counts[c] += 1;
+        }
+    }
+    let old = translator.alphabet();
+    let alphabet = order.arrange(old.iter().cloned().zip(counts).collect());
+    if alphabet == old {
+        return (translator, arena);
+    }
+    let reordered = Translator::from_alphabet(translator.normalizer, &alphabet);
+    // the new number of each letter by its old number
+    let numbers = old
+        .iter()
+        .map(|c| alphabet.iter().position(|a| a == c).unwrap())
+        .collect::<Vec<_>>();
+    let mut t = TrieArenaBuilder::new();
+    for word in words {
+        let translation = word.iter().map(|&c| numbers[c]).collect::<Vec<_>>();
+        match arena.weights() {
+            Some(_) => t.add_weighted(&translation, arena.weight(&word)),
+            None => t.add(&translation),
+        }
+    }
+    (reordered, t.build())
+}
+
+// collects the words reachable from a node
+fn gather(arena: &TrieArena, node: usize, word: &mut Vec<usize>, words: &mut Vec<Vec<usize>>) {
+    if arena.nodes()[node].terminal {
+        words.push(word.clone());
+    }
+    for edge in arena.children(node) {
+        word.push(edge.character as usize);
+        gather(arena, edge.target as usize, word, words);
+        word.pop();
+    }
+}
+
+/// Reads a line-delimited word list, ignoring any compiled version.
+pub fn read_text(path: &str) -> io::Result<(Translator, TrieArena)> {
+    parse(&text(read(path)?)?)
+}
+
+/// Saves a dictionary in compiled form.
+pub fn write(path: &str, translator: &Translator, arena: &TrieArena) -> io::Result<()> {
+    let body = encode(translator, arena);
+    let mut bytes = Vec::with_capacity(HEADER_LENGTH + body.len());
+    bytes.extend_from_slice(MAGIC);
+    push_u32(&mut bytes, VERSION);
+    bytes.extend_from_slice(&checksum(&body).to_le_bytes());
+    bytes.extend_from_slice(&body);
+    File::create(path)?.write_all(&bytes)
+}
+
+/// The checksum a compiled form of this dictionary would have. Dictionaries
+/// with the same fingerprint hold the same words.
+pub fn fingerprint(translator: &Translator, arena: &TrieArena) -> u64 {
+    checksum(&encode(translator, arena))
+}
+
+/// FNV-1a, a fast hash adequate to detect damaged files.
+pub fn checksum(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &b in bytes {
+        hash ^= u64::from(b);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+// the body of a compiled dictionary, everything after the header
+fn encode(translator: &Translator, arena: &TrieArena) -> Vec<u8> {
+    let mut body = vec![];
+    let alphabet = translator.alphabet();
+    push_u32(&mut body, alphabet.len() as u32);
+    for c in alphabet {
+        push_u32(&mut body, c as u32);
+    }
+    // any nodes left unreachable by removals are written too; the root is
+    // always last
+    let nodes = arena.nodes();
+    push_u32(&mut body, nodes.len() as u32);
+    for node in nodes {
+        body.push(node.terminal as u8);
+        push_u32(&mut body, node.end - node.start);
+        for edge in &arena.edges()[node.start as usize..node.end as usize] {
+            push_u32(&mut body, edge.character);
+            push_u32(&mut body, edge.target);
+        }
+    }
+    let weights = arena.weights().unwrap_or(&[]);
+    push_u32(&mut body, weights.len() as u32);
+    for weight in weights {
+        body.extend_from_slice(&weight.to_le_bytes());
+    }
+    body
+}
+
+fn read(path: &str) -> io::Result<Vec<u8>> {
+    let mut bytes = vec![];
+    File::open(path)?.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn text(bytes: Vec<u8>) -> io::Result<String> {
+    String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn invalid(reason: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("bad compiled dictionary: {}", reason),
+    )
+}
+
+fn push_u32(bytes: &mut Vec<u8>, n: u32) {
+    bytes.extend_from_slice(&n.to_le_bytes());
+}
+
+fn decode(bytes: &[u8]) -> io::Result<(Translator, TrieArena)> {
+    if bytes.len() < HEADER_LENGTH || !bytes.starts_with(MAGIC) {
+        return Err(invalid("no header"));
+    }
+    let mut reader = Reader {
+        bytes,
+        offset: MAGIC.len(),
+    };
+    let version = reader.u32()?;
+    if version != VERSION {
+        return Err(invalid(&format!(
+            "version {} where {} was expected",
+            version, VERSION
+        )));
+    }
+    let mut sum = [0; 8];
+    sum.copy_from_slice(&bytes[8..HEADER_LENGTH]);
+    if u64::from_le_bytes(sum) != checksum(&bytes[HEADER_LENGTH..]) {
+        return Err(invalid("checksum mismatch"));
+    }
+    reader.offset = HEADER_LENGTH;
+    // the counts are not trusted to size allocations beyond what the bytes
+    // left could hold
+    let n = reader.u32()? as usize;
+    let mut alphabet = Vec::with_capacity(n.min(reader.remaining() / 4));
+    for _ in 0..n {
+        match ::std::char::from_u32(reader.u32()?) {
+            Some(c) => alphabet.push(c),
+            None => return Err(invalid("bad character")),
+        }
+    }
+    let count = reader.u32()? as usize;
+    if count == 0 {
+        return Err(invalid("no root"));
+    }
+    // a node is at least a terminal flag and an edge count
+    let mut nodes = Vec::with_capacity(count.min(reader.remaining() / 5));
+    let mut edges = vec![];
+    for _ in 0..count {
+        let node = reader.node(n, nodes.len(), &mut edges)?;
+        nodes.push(node);
+    }
+    let mut arena = TrieArena::from_parts(nodes, edges);
+    let count = reader.u32()? as usize;
+    if count > 0 {
+        let mut weights = Vec::with_capacity(count.min(reader.remaining() / 4));
+        for _ in 0..count {
+            weights.push(f32::from_bits(reader.u32()?));
+        }
+        arena = match arena.weigh(weights) {
+            Some(arena) => arena,
+            None => return Err(invalid("weights do not match words")),
+        };
+    }
+    if reader.offset != bytes.len() {
+        return Err(invalid("trailing bytes"));
+    }
+    Ok((Translator::from_alphabet(normalize, &alphabet), arena))
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn remaining(&self) -> usize {
+        self.bytes.len() - self.offset
+    }
+    fn u8(&mut self) -> io::Result<u8> {
+        match self.bytes.get(self.offset) {
+            Some(&b) => {
+                self.offset += 1;
+                Ok(b)
+            }
+            None => Err(invalid("truncated")),
+        }
+    }
+    fn u32(&mut self) -> io::Result<u32> {
+        if self.offset + 4 > self.bytes.len() {
+            return Err(invalid("truncated"));
+        }
+        let mut n = [0; 4];
+        n.copy_from_slice(&self.bytes[self.offset..self.offset + 4]);
+        self.offset += 4;
+        Ok(u32::from_le_bytes(n))
+    }
+    // read a node whose offset in the arena is `id`, appending its edges
+    fn node(&mut self, alphabet_size: usize, id: usize, edges: &mut Vec<Edge>) -> io::Result<Node> {
+        let terminal = self.u8()? != 0;
+        let count = self.u32()?;
+        let start = edges.len() as u32;
+        for _ in 0..count {
+            let character = self.u32()?;
+            let target = self.u32()?;
+            // edges must be sorted and point to earlier nodes
+            let sorted =
+                edges.len() as u32 == start || edges[edges.len() - 1].character < character;
+            if character as usize >= alphabet_size || !sorted || target as usize >= id {
+                return Err(invalid("bad child"));
+            }
+            edges.push(Edge { character, target });
+        }
+        Ok(Node {
+            terminal,
+            start,
+            end: edges.len() as u32,
+        })
+    }
+}
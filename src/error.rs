@@ -0,0 +1,112 @@
+//! the ways rana can fail
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// Everything that can go wrong in setting up or running an anagram search.
+/// Each variant has its own exit code for use by the `rana` executable.
+#[derive(Debug)]
+pub enum RanaError {
+    /// A setting, such as a thread count or a minimum word length, which is
+    /// malformed or out of range.
+    InvalidArgument { name: String, reason: String },
+    /// The dictionary could not be opened or read.
+    UnreadableDictionary { path: String, source: io::Error },
+    /// A word contains a character found in no word in the dictionary. The
+    /// word is split into the part before the unknown character and the rest.
+    UnknownCharacter {
+        word: String,
+        good: String,
+        bad: String,
+    },
+    /// A word to include in the anagrams uses a character the phrase has run
+    /// out of. `index` is the offset of this character in the normalized
+    /// word.
+    UnavailableCharacter { word: String, index: usize },
+    /// Some output file, such as a compiled dictionary, could not be written.
+    UnwritableFile { path: String, source: io::Error },
+    /// Some input file other than the dictionary, such as a memo file, could
+    /// not be read.
+    UnreadableFile { path: String, source: io::Error },
+}
+
+impl RanaError {
+    /// The exit code the `rana` executable uses for this error.
+    ///
+    /// * 2 -- invalid argument
+    /// * 3 -- unreadable dictionary
+    /// * 4 -- unknown character
+    /// * 5 -- unavailable character
+    /// * 6 -- unwritable file
+    /// * 7 -- unreadable file
+    ///
+    /// Errors detected by the command line parser itself exit with 1.
+    pub fn exit_code(&self) -> i32 {
+        match *self {
+            RanaError::InvalidArgument { .. } => 2,
+            RanaError::UnreadableDictionary { .. } => 3,
+            RanaError::UnknownCharacter { .. } => 4,
+            RanaError::UnavailableCharacter { .. } => 5,
+            RanaError::UnwritableFile { .. } => 6,
+            RanaError::UnreadableFile { .. } => 7,
+        }
+    }
+    /// Shorthand for an `InvalidArgument`.
+    pub fn invalid(name: &str, reason: &str) -> RanaError {
+        RanaError::InvalidArgument {
+            name: name.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for RanaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RanaError::InvalidArgument {
+                ref name,
+                ref reason,
+            } => write!(f, "invalid {}: {}", name, reason),
+            RanaError::UnreadableDictionary {
+                ref path,
+                ref source,
+            } => write!(f, "could not read dictionary {}: {}", path, source),
+            RanaError::UnknownCharacter {
+                ref word,
+                ref good,
+                ref bad,
+            } => write!(
+                f,
+                "character in {} not present in any word in dictionary:\n\n\t{}-->{}",
+                word, good, bad
+            ),
+            RanaError::UnavailableCharacter { ref word, index } => write!(
+                f,
+                "attempt to use unavailable character in {}:\n\n\t{}-->{}",
+                word,
+                word.chars().take(index).collect::<String>(),
+                word.chars().skip(index).collect::<String>()
+            ),
+            RanaError::UnwritableFile {
+                ref path,
+                ref source,
+            } => write!(f, "could not write {}: {}", path, source),
+            RanaError::UnreadableFile {
+                ref path,
+                ref source,
+            } => write!(f, "could not read {}: {}", path, source),
+        }
+    }
+}
+
+impl Error for RanaError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            RanaError::UnreadableDictionary { ref source, .. }
+            | RanaError::UnwritableFile { ref source, .. }
+            | RanaError::UnreadableFile { ref source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -1,10 +1,13 @@
 //! thread model
 
+use error::RanaError;
 use std::cmp;
+use std::collections::BinaryHeap;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::mpsc::{Receiver, Sender};
-use std::sync::{mpsc, Arc, Mutex};
+use std::sync::{mpsc, Arc, Condvar, Mutex};
 use std::thread::{self, Builder};
+use std::time::Duration;
 
 /// Messages the monitor thread sends to worker threads to control their
 /// activity.
@@ -28,8 +31,16 @@ enum WorkerMessage {
 /// to see whether it can be shipped. If not, they improve it, making some
 /// number of improved items.
 pub trait WorkerFun<I: Send + 'static>: Send + Sync + 'static {
-    fn improve(&self, I) -> Vec<I>;
-    fn inspect(&self, &I) -> bool;
+    fn improve(&self, item: I) -> Vec<I>;
+    fn inspect(&self, item: &I) -> bool;
+}
+
+/// A `WorkerFun` whose items can be ranked, so the most promising may be
+/// worked on first. An item's `bound` must be at least as great as the bound
+/// of any item improved from it, and for an item which passes inspection it
+/// is the item's own score.
+pub trait RankedWorkerFun<I: Send + 'static>: WorkerFun<I> {
+    fn bound(&self, item: &I) -> f64;
 }
 
 /// Start the factory going. The `roster` is the number of workers. The
@@ -37,23 +48,24 @@ pub trait WorkerFun<I: Send + 'static>: Send + Sync + 'static {
 /// items to keep in reserve for workers that run low in their personal work
 /// queues. The `materials` are the initial items requiring improvement. The
 /// `fun` provides the specifications for what the workers will do to improve
-/// or inspect their work.
+/// or inspect their work. Both the `roster` and the `slop_factor` must be
+/// positive.
 pub fn manufacture<I, W>(
     roster: usize,
     slop_factor: usize,
     materials: Vec<I>,
     fun: Arc<W>,
-) -> (Receiver<Option<I>>, Arc<AtomicBool>)
+) -> Result<(Receiver<Option<I>>, Arc<AtomicBool>), RanaError>
 where
     I: Send + 'static,
     W: WorkerFun<I>,
 {
     // set up work sharing mechanism
     if roster == 0 {
-        panic!("roster must be greater than 0");
+        return Err(RanaError::invalid("roster", "must be greater than 0"));
     }
     if slop_factor == 0 {
-        panic!("slop_factor must be greater than 0");
+        return Err(RanaError::invalid("slop factor", "must be greater than 0"));
     }
     let maximum_shared = roster * slop_factor;
     let threshold = roster;
@@ -80,9 +92,10 @@ where
         );
     }
     thread::spawn(move || supervize(roster, workers, stamps, container));
-    (truck, kill_switch)
+    Ok((truck, kill_switch))
 }
 
+#[allow(clippy::too_many_arguments)]
 fn work<I, W>(
     i: usize,
     had: Arc<AtomicUsize>,
@@ -100,7 +113,7 @@ fn work<I, W>(
 {
     let (worker, in_box) = mpsc::channel::<BossMessage>();
     workers.lock().unwrap().push(worker);
-    let bob = Builder::new().name(format!("{}", i).into());
+    let bob = Builder::new().name(format!("{}", i));
     bob.spawn(move || {
         let mut hopper = vec![];
         for message in in_box {
@@ -135,7 +148,7 @@ fn work<I, W>(
                                     cmp::min(own - 1, maximum_shared - currently_shared);
                                 had.fetch_add(tithe, Ordering::Relaxed);
                                 let mut belt = belt.lock().unwrap();
-                                if widgets.len() > 0 {
+                                if !widgets.is_empty() {
                                     if widgets.len() <= tithe {
                                         tithe -= widgets.len();
                                         for _ in 0..widgets.len() {
@@ -182,18 +195,18 @@ fn supervize<I>(
         match message {
             WorkerMessage::Slain => {
                 container.send(None).ok();
-                let foo = workers.lock().unwrap();
+                let workers = workers.lock().unwrap();
                 for &i in idled.iter() {
-                    if let Some(w) = foo.get(i) {
+                    if let Some(w) = workers.get(i) {
                         w.send(BossMessage::Go).ok();
                     }
                 }
                 break;
             }
             WorkerMessage::WakeUp => {
-                let foo = workers.lock().unwrap();
+                let workers = workers.lock().unwrap();
                 for &i in idled.iter() {
-                    if let Some(w) = foo.get(i) {
+                    if let Some(w) = workers.get(i) {
                         w.send(BossMessage::Go).ok();
                     }
                 }
@@ -211,3 +224,180 @@ fn supervize<I>(
         }
     }
 }
+
+/// An item in a best-first factory's queue, with its bound and whether it
+/// passed inspection.
+struct Ranked<I> {
+    bound: f64,
+    finished: bool,
+    item: I,
+}
+
+impl<I> PartialEq for Ranked<I> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == cmp::Ordering::Equal
+    }
+}
+
+impl<I> Eq for Ranked<I> {}
+
+impl<I> PartialOrd for Ranked<I> {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<I> Ord for Ranked<I> {
+    // higher bounds first, and among equals finished items first
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        self.bound
+            .total_cmp(&other.bound)
+            .then(self.finished.cmp(&other.finished))
+    }
+}
+
+/// The state shared by the workers of a best-first factory.
+struct Frontier<I> {
+    queue: BinaryHeap<Ranked<I>>,
+    // the bound of the item each worker is improving, if any
+    working: Vec<Option<f64>>,
+    shipped: usize,
+    closed: bool,
+}
+
+// how long an idle worker waits before checking the kill switch again
+const PATIENCE: Duration = Duration::from_millis(10);
+
+/// Like `manufacture`, but the workers share a single priority queue rather
+/// than keeping their own hoppers, and always work on the item with the
+/// highest bound. Finished items are shipped in order of their bounds: one is
+/// only shipped when no item in the queue or in a worker's hands has a
+/// higher bound, so none better can turn up later. Thus the first `n` items
+/// shipped are the `n` best. If there is a `limit`, the factory closes after
+/// shipping that many items. The `roster` must be positive.
+pub fn manufacture_best_first<I, W>(
+    roster: usize,
+    materials: Vec<I>,
+    fun: Arc<W>,
+    limit: Option<usize>,
+) -> Result<(Receiver<Option<I>>, Arc<AtomicBool>), RanaError>
+where
+    I: Send + 'static,
+    W: RankedWorkerFun<I>,
+{
+    if roster == 0 {
+        return Err(RanaError::invalid("roster", "must be greater than 0"));
+    }
+    let queue = materials
+        .into_iter()
+        .map(|item| rank(&*fun, item))
+        .collect();
+    let frontier = Arc::new((
+        Mutex::new(Frontier {
+            queue,
+            working: vec![None; roster],
+            shipped: 0,
+            closed: false,
+        }),
+        Condvar::new(),
+    ));
+    let (container, truck) = mpsc::channel::<Option<I>>();
+    let kill_switch = Arc::new(AtomicBool::new(false));
+    for i in 0..roster {
+        let frontier = frontier.clone();
+        let container = container.clone();
+        let fun = fun.clone();
+        let kill_switch = kill_switch.clone();
+        Builder::new()
+            .name(format!("{}", i))
+            .spawn(move || rank_and_file(i, &frontier, &container, &*fun, &kill_switch, limit))
+            .unwrap();
+    }
+    Ok((truck, kill_switch))
+}
+
+fn rank<I, W>(fun: &W, item: I) -> Ranked<I>
+where
+    I: Send + 'static,
+    W: RankedWorkerFun<I>,
+{
+    Ranked {
+        bound: fun.bound(&item),
+        finished: fun.inspect(&item),
+        item,
+    }
+}
+
+// the life of a worker in a best-first factory
+// is_none_or is too new for the compilers rana still builds with
+#[allow(clippy::unnecessary_map_or)]
+fn rank_and_file<I, W>(
+    i: usize,
+    frontier: &(Mutex<Frontier<I>>, Condvar),
+    container: &Sender<Option<I>>,
+    fun: &W,
+    kill_switch: &AtomicBool,
+    limit: Option<usize>,
+) where
+    I: Send + 'static,
+    W: RankedWorkerFun<I>,
+{
+    let (ref lock, ref changed) = *frontier;
+    loop {
+        // find something to improve, shipping whatever can be shipped
+        let item = {
+            let mut state = lock.lock().unwrap();
+            loop {
+                if state.closed || kill_switch.load(Ordering::Relaxed) {
+                    return;
+                }
+                if limit.is_some_and(|n| state.shipped >= n) {
+                    state.closed = true;
+                    container.send(None).ok();
+                    changed.notify_all();
+                    return;
+                }
+                let busiest = state
+                    .working
+                    .iter()
+                    .filter_map(|&b| b)
+                    .fold(None, |m: Option<f64>, b| Some(m.map_or(b, |m| m.max(b))));
+                let ready = match state.queue.peek() {
+                    Some(top) => !top.finished || busiest.map_or(true, |b| top.bound >= b),
+                    None => {
+                        if busiest.is_none() {
+                            // nothing is left to do
+                            state.closed = true;
+                            container.send(None).ok();
+                            changed.notify_all();
+                            return;
+                        }
+                        false
+                    }
+                };
+                if !ready {
+                    // wait for the other workers to finish what they hold
+                    state = changed.wait_timeout(state, PATIENCE).unwrap().0;
+                    continue;
+                }
+                let top = state.queue.pop().unwrap();
+                if top.finished {
+                    container.send(Some(top.item)).ok();
+                    state.shipped += 1;
+                } else {
+                    state.working[i] = Some(top.bound);
+                    break top.item;
+                }
+            }
+        };
+        let widgets: Vec<Ranked<I>> = fun
+            .improve(item)
+            .into_iter()
+            .map(|widget| rank(fun, widget))
+            .collect();
+        let mut state = lock.lock().unwrap();
+        state.queue.extend(widgets);
+        state.working[i] = None;
+        changed.notify_all();
+    }
+}
@@ -0,0 +1,100 @@
+//! ways of numbering the letters of a dictionary's alphabet
+//!
+//! The number a `Translator` gives each letter decides how the trie
+//! branches, which letters a `CharCount`'s `first` and `last` bounds skip,
+//! and which letter the rarest letter strategy requires at each step.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+use util::normalize;
+
+/// The order in which a `Translator` numbers letters. Letters equally
+/// frequent are numbered in alphabetical order, so a dictionary always has
+/// the same numbering.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum LetterOrder {
+    /// the most frequent letter of the dictionary first
+    #[default]
+    Frequency,
+    /// the least frequent letter of the dictionary first
+    Rarity,
+    /// in alphabetical order
+    Alphabetical,
+    /// the letters given first, in the order given, then any others by
+    /// frequency
+    Custom(Vec<char>),
+}
+
+impl LetterOrder {
+    /// The names by which orders are parsed, in the order of the variants.
+    /// Any other string of distinct letters is parsed as a custom order.
+    pub const NAMES: &'static [&'static str] = &["frequency", "rarity", "alphabetical"];
+    /// Arranges letters and the number of times each occurs in the
+    /// dictionary into numeric order.
+    pub fn arrange(&self, counts: Vec<(char, usize)>) -> Vec<char> {
+        let mut counts = counts;
+        let place = |c: char| -> Option<usize> {
+            match *self {
+                LetterOrder::Custom(ref letters) => letters.iter().position(|&l| l == c),
+                _ => None,
+            }
+        };
+        counts.sort_by(|&(c1, n1), &(c2, n2)| {
+            let order = match *self {
+                LetterOrder::Frequency => n2.cmp(&n1),
+                LetterOrder::Rarity => n1.cmp(&n2),
+                LetterOrder::Alphabetical => Ordering::Equal,
+                // a placed letter precedes any unplaced one
+                LetterOrder::Custom(_) => match (place(c1), place(c2)) {
+                    (Some(a), Some(b)) => a.cmp(&b),
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => n2.cmp(&n1),
+                },
+            };
+            order.then(c1.cmp(&c2))
+        });
+        counts.into_iter().map(|(c, _)| c).collect()
+    }
+}
+
+impl FromStr for LetterOrder {
+    type Err = String;
+    fn from_str(s: &str) -> Result<LetterOrder, String> {
+        match s {
+            "frequency" => Ok(LetterOrder::Frequency),
+            "rarity" => Ok(LetterOrder::Rarity),
+            "alphabetical" => Ok(LetterOrder::Alphabetical),
+            _ => {
+                let letters = normalize(s).chars().collect::<Vec<_>>();
+                if letters.is_empty() || letters.len() != s.trim().chars().count() {
+                    return Err(format!(
+                        "{} is neither one of {} nor a list of letters",
+                        s,
+                        LetterOrder::NAMES.join(", ")
+                    ));
+                }
+                for (i, c) in letters.iter().enumerate() {
+                    if letters[..i].contains(c) {
+                        return Err(format!("{} is listed more than once", c));
+                    }
+                }
+                Ok(LetterOrder::Custom(letters))
+            }
+        }
+    }
+}
+
+impl fmt::Display for LetterOrder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LetterOrder::Frequency => write!(f, "{}", LetterOrder::NAMES[0]),
+            LetterOrder::Rarity => write!(f, "{}", LetterOrder::NAMES[1]),
+            LetterOrder::Alphabetical => write!(f, "{}", LetterOrder::NAMES[2]),
+            LetterOrder::Custom(ref letters) => {
+                write!(f, "{}", letters.iter().collect::<String>())
+            }
+        }
+    }
+}
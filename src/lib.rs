@@ -1,7 +1,21 @@
+extern crate num_cpus;
+pub mod anagrammer;
+pub mod cache;
 pub mod cli;
+pub mod count;
+pub mod dictionary;
+pub mod error;
 pub mod factory;
+pub mod letter_order;
+pub mod memo;
+pub mod order;
+pub mod prune;
+pub mod score;
+pub mod strategy;
+pub mod template;
 pub mod trie;
 pub mod util;
 extern crate rand;
+extern crate regex;
 #[macro_use]
 extern crate clap;
@@ -1,18 +1,16 @@
-extern crate ranagrams;
-use factory::WorkerFun;
-use ranagrams::cli;
-use ranagrams::factory;
-use ranagrams::trie::{Trie, TrieNodeBuilder};
-use ranagrams::util::{normalize, ToDo, Translator};
-use std::collections::{HashMap, HashSet};
-use std::fs::File;
-use std::io::Read;
-use std::sync::atomic::Ordering;
-use std::sync::Arc;
 extern crate clap;
+extern crate ranagrams;
 use clap::ArgMatches;
+use ranagrams::anagrammer::{Anagram, AnagrammerBuilder};
+use ranagrams::cache;
+use ranagrams::cli;
+use ranagrams::dictionary;
+use ranagrams::error::RanaError;
+use ranagrams::letter_order::LetterOrder;
+use ranagrams::order::Order;
+use ranagrams::score::Combiner;
+use ranagrams::strategy::Strategy;
 extern crate num_cpus;
-use std::ops::Deref;
 use std::process;
 extern crate dirs;
 use dirs::home_dir;
@@ -26,11 +24,9 @@ fn main() {
     } else {
         None
     };
-    let options = cli::parse(&cpus, default_dir.as_ref().map(String::deref)).get_matches();
+    let options = cli::parse(&cpus, default_dir.as_deref()).get_matches();
     if options.is_present("long-help") {
-        cli::parse(&cpus, default_dir.as_ref().map(String::deref))
-            .print_help()
-            .ok();
+        cli::parse(&cpus, default_dir.as_deref()).print_help().ok();
         println!("\n\n{}", cli::long_help());
         process::exit(0)
     }
@@ -38,325 +34,256 @@ fn main() {
         println!("\n{}", include_str!("../rana.txt"));
         process::exit(0)
     }
-    let threads = if options.is_present("set") && !options.is_present("strict") {
+    if let Some(compile_options) = options.subcommand_matches("compile") {
+        compile(compile_options).unwrap_or_else(|e| fail(e, &options));
+        process::exit(0)
+    }
+    let threads = if options.is_present("set")
+        && !(options.is_present("strict") || options.is_present("prove"))
+    {
         // only one thread will ever be used
         1
     } else {
-        match usize::from_str_radix(options.value_of("threads").unwrap(), 10) {
-            Err(why) => {
-                eprintln!("error parsing thread count: {}\n\n{}", why, options.usage());
-                process::exit(1)
-            }
-            Ok(threads) => threads,
-        }
+        number(&options, "threads", "thread count").unwrap_or_else(|e| fail(e, &options))
     };
     let use_limit = options.is_present("limit");
     let limit = if use_limit {
-        match usize::from_str_radix(options.value_of("limit").unwrap(), 10) {
-            Err(why) => {
-                eprintln!(
-                    "could not parse anagram limit: {}\n\n{}",
-                    why,
-                    options.usage()
-                );
-                process::exit(1)
-            }
-            Ok(limit) => limit,
-        }
+        number(&options, "limit", "anagram limit").unwrap_or_else(|e| fail(e, &options))
     } else {
         0
     };
     let min_word_length = if options.is_present("min") {
-        match usize::from_str_radix(options.value_of("min").unwrap(), 10) {
-            Err(why) => {
-                eprintln!(
-                    "could not parse minimum word length: {}\n\n{}",
-                    why,
-                    options.usage()
-                );
-                process::exit(1)
-            }
-            Ok(min) => {
-                if min == 0 {
-                    eprintln!(
-                        "minimum word length must be positive\n\n{}",
-                        options.usage()
-                    );
-                    process::exit(1)
-                } else {
-                    min
-                }
-            }
-        }
+        number(&options, "min", "minimum word length").unwrap_or_else(|e| fail(e, &options))
     } else {
         1
     };
-    let trie_word_length = if options.is_present("strict") || options.is_present("prove") {
-        1
-    } else {
-        min_word_length
-    };
-    let mut trie = make_trie(&options, trie_word_length);
-
-    // create initial character count
-    let mut cc = trie
-        .translator
-        .count(&normalize(""))
-        .expect("no luck with the char count");
-    // add all the words to anagramize
+    let mut builder = AnagrammerBuilder::new()
+        .minimum_word_length(min_word_length)
+        .threads(threads)
+        .cache(!(options.is_present("no_cache") || options.is_present("set")))
+        .shuffle(options.is_present("random"));
+    if let Some(strategy) = options.value_of("strategy") {
+        let strategy = strategy
+            .parse::<Strategy>()
+            .map_err(|why| RanaError::invalid("strategy", &why))
+            .unwrap_or_else(|e| fail(e, &options));
+        builder = builder.strategy(strategy);
+    }
+    if let Some(order) = options.value_of("letter_order") {
+        let order = order
+            .parse::<LetterOrder>()
+            .map_err(|why| RanaError::invalid("letter order", &why))
+            .unwrap_or_else(|e| fail(e, &options));
+        builder = builder.letter_order(order);
+    }
+    if let Some(seed) = options.value_of("seed") {
+        let seed = seed
+            .parse::<u64>()
+            .map_err(|why| RanaError::invalid("seed", &why.to_string()))
+            .unwrap_or_else(|e| fail(e, &options));
+        builder = builder.seed(seed);
+    }
+    if options.is_present("max") {
+        let n =
+            number(&options, "max", "maximum word length").unwrap_or_else(|e| fail(e, &options));
+        builder = builder.maximum_word_length(n);
+    }
+    if options.is_present("words") {
+        let n = number(&options, "words", "word count").unwrap_or_else(|e| fail(e, &options));
+        builder = builder.minimum_words(n).maximum_words(n);
+    }
+    if options.is_present("min_words") {
+        let n =
+            number(&options, "min_words", "minimum words").unwrap_or_else(|e| fail(e, &options));
+        builder = builder.minimum_words(n);
+    }
+    if options.is_present("max_words") {
+        let n =
+            number(&options, "max_words", "maximum words").unwrap_or_else(|e| fail(e, &options));
+        builder = builder.maximum_words(n);
+    }
+    if let Some(requirements) = options.values_of("require") {
+        for requirement in requirements {
+            let (count, pattern) =
+                requirement_count(requirement).unwrap_or_else(|e| fail(e, &options));
+            builder = builder.require_match(pattern, count);
+        }
+    }
+    if let Some(patterns) = options.values_of("forbid") {
+        for pattern in patterns {
+            builder = builder.forbid_match(pattern);
+        }
+    }
+    if let Some(template) = options.value_of("pattern") {
+        builder = builder.pattern(template);
+    }
+    if options.is_present("leftover") {
+        let n = number(&options, "leftover", "leftover").unwrap_or_else(|e| fail(e, &options));
+        builder = builder.leftover(n);
+    }
+    if options.is_present("use_at_least") {
+        let n =
+            number(&options, "use_at_least", "use at least").unwrap_or_else(|e| fail(e, &options));
+        builder = builder.use_at_least(n);
+    }
+    if let Some(size) = options.value_of("cache_memory") {
+        let bytes = cache::parse_size(size)
+            .map_err(|why| RanaError::invalid("cache memory", &why))
+            .unwrap_or_else(|e| fail(e, &options));
+        builder = builder.cache_memory(bytes);
+    }
+    if let Some(dictionary) = options.value_of("dictionary") {
+        builder = builder.dictionary_file(dictionary);
+    }
     for word in options.values_of("phrase").unwrap() {
-        if let Some(usizes) = trie.translator.translate(word) {
-            if !cc.add(usizes) {
-                dictionary_error(word, &trie)
-            }
-        } else {
-            dictionary_error(word, &trie)
+        builder = builder.phrase(word);
+    }
+    if let Some(words) = options.values_of("include") {
+        for word in words {
+            builder = builder.include(word);
         }
     }
-    // subtract the words to include
-    let prefixed = options.is_present("include");
-    let mut prefix = String::new();
-    if prefixed {
-        for word in options.values_of("include").unwrap() {
-            if let Some(usizes) = trie.translator.translate(word) {
-                match cc.subtract(usizes) {
-                    Some((i, copy)) => {
-                        let normalized = trie.translator.etalsnart(&copy).unwrap();
-                        eprintln!(
-                            "attempt to use unavailable character in {}:\n\n\t{}-->{}",
-                            &normalized,
-                            &normalized[0..i],
-                            &normalized[i..]
-                        );
-                        process::exit(1)
-                    }
-                    None => {
-                        prefix.push_str(word);
-                        prefix.push(' ');
-                    }
-                }
-            } else {
-                dictionary_error(word, &trie)
-            }
+    if let Some(words) = options.values_of("exclude") {
+        for word in words {
+            builder = builder.exclude(word);
         }
     }
-    cc.set_limits();
-    trie = trie.optimize(cc.clone());
+    if let Some(memo) = options.value_of("memo") {
+        builder = builder.memo_file(memo);
+    }
+    let mut anagrammer = builder.build().unwrap_or_else(|e| fail(e, &options));
 
     if options.is_present("set") {
-        if options.is_present("strict") || options.is_present("prove") {
-            let prove = options.is_present("prove");
-            let sort_key = Vec::with_capacity(0);
-            // find candidate words -- these are embedded in the initial character count and thus
-            // are the only ones that might be in an anagram
-            let mut found: Vec<String> = trie
-                .words_for(Arc::new(cc.clone()), &sort_key, &true)
-                .into_iter()
-                .map(|(chars, _)| trie.translator.etalsnart(&chars).unwrap())
-                .collect();
-            found.sort();
-            let mut cache: HashMap<String, String> = HashMap::new();
-            for word in found {
-                if word.len() >= min_word_length {
-                    if cache.contains_key(&word) {
-                        println!("{}", word);
-                        if prove {
-                            println!("\t{}", cache.get(&word).unwrap());
-                        }
-                    } else {
-                        if let Some(usizes) = trie.translator.translate(word.as_str()) {
-                            // can we make a least one anagram with the remainder after we subtract this word?
-                            let mut cc = cc.clone();
-                            cc.subtract(usizes.to_vec());
-                            let materials = vec![ToDo::seed(cc)];
-                            let noah = Arc::new(AnagramFun { root: trie.clone() });
-                            let (messages, kill_switch) =
-                                factory::manufacture(threads, 3, materials, noah.clone());
-                            if let Some(Some(done)) = messages.iter().next() {
-                                kill_switch.store(true, Ordering::Relaxed);
-                                println!("{}", word);
-                                let s = if prove {
-                                    word.clone() + " " + &trie.stringify(done)
-                                } else {
-                                    trie.stringify(done)
-                                };
-                                if prove {
-                                    println!("\t{}", s);
-                                } else {
-                                    // only do this if we're not proving, because we aren't always saving the proof in the cache
-                                    for ow in words_in_word(&word, noah.clone(), threads) {
-                                        if ow.len() >= min_word_length
-                                            && ow > word
-                                            && !cache.contains_key(&ow)
-                                        {
-                                            cache.insert(ow, s.clone());
-                                        }
-                                    }
-                                }
-                                for other_word in s.as_str().split(" ") {
-                                    if prove {
-                                        if other_word.len() >= min_word_length && other_word > &word
-                                        {
-                                            cache.insert(String::from(other_word), s.clone());
-                                        }
-                                    } else {
-                                        for ow in words_in_word(other_word, noah.clone(), threads) {
-                                            if ow.len() >= min_word_length
-                                                && ow > word
-                                                && !cache.contains_key(&ow)
-                                            {
-                                                cache.insert(ow, s.clone());
-                                            }
-                                        }
-                                    }
-                                }
-                            } else {
-                                trie.remove(&usizes);
-                            }
-                        }
-                    }
-                }
+        if options.is_present("prove") {
+            for (word, proof) in anagrammer.proven_words_in() {
+                println!("{}\n\t{}", word, proof);
+            }
+        } else if options.is_present("strict") {
+            for word in anagrammer.strict_words_in() {
+                println!("{}", word);
             }
         } else {
-            let sort_key = Vec::with_capacity(0);
-            let mut found: Vec<String> = trie
-                .words_for(Arc::new(cc), &sort_key, &true)
-                .into_iter()
-                .map(|(chars, _)| trie.translator.etalsnart(&chars).unwrap())
-                .collect();
-            found.sort();
-            for word in found {
+            for word in anagrammer.words_in() {
                 println!("{}", word);
             }
         }
+    } else if options.is_present("count") {
+        let count = anagrammer.count().unwrap_or_else(|e| fail(e, &options));
+        println!("{}", count);
     } else {
-        let mut count = 0;
-        let materials = vec![ToDo::seed(cc)];
-        let noah = Arc::new(AnagramFun { root: trie });
-        let mine = noah.clone();
-        let (messages, kill_switch) = factory::manufacture(threads, 3, materials, noah);
-        for m in messages {
-            if let Some(todo) = m {
-                if prefixed {
-                    print!("{}", prefix);
-                }
-                println!("{}", mine.root.stringify(todo));
-                if use_limit {
-                    count += 1;
-                    if count == limit {
-                        kill_switch.store(true, Ordering::Relaxed);
-                        break;
-                    }
-                }
-            } else {
-                break;
-            }
+        let combiner = match options.value_of("score") {
+            Some(name) => name
+                .parse::<Combiner>()
+                .map_err(|why| RanaError::invalid("score", &why))
+                .unwrap_or_else(|e| fail(e, &options)),
+            None => Combiner::default(),
+        };
+        let anagrams: Box<dyn Iterator<Item = Anagram>> = if options.is_present("top") {
+            let k = number(&options, "top", "top count").unwrap_or_else(|e| fail(e, &options));
+            Box::new(anagrammer.top(k, combiner))
+        } else if options.is_present("sample") {
+            let n = number(&options, "sample", "sample size").unwrap_or_else(|e| fail(e, &options));
+            let sample = anagrammer.sample(n).unwrap_or_else(|e| fail(e, &options));
+            Box::new(sample.into_iter())
+        } else if let Some(order) = options.value_of("sorted") {
+            let order = order
+                .parse::<Order>()
+                .map_err(|why| RanaError::invalid("sorted", &why))
+                .unwrap_or_else(|e| fail(e, &options));
+            let sorted = anagrammer
+                .sorted(order)
+                .unwrap_or_else(|e| fail(e, &options));
+            Box::new(sorted)
+        } else if options.is_present("sort") {
+            Box::new(anagrammer.anagrams_by_score(combiner).into_iter())
+        } else {
+            Box::new(anagrammer.anagrams())
+        };
+        let anagrams: Box<dyn Iterator<Item = Anagram>> = if use_limit {
+            Box::new(anagrams.take(limit))
+        } else {
+            Box::new(anagrams)
+        };
+        for anagram in anagrams {
+            println!("{}", anagram);
         }
     }
-}
-
-// fetch the anagrams of a word -- useful for --strict and --prove
-fn words_in_word(word: &str, noah: Arc<AnagramFun>, threads: usize) -> Vec<String> {
-    let mut cc = noah
-        .root
-        .translator
-        .count(&normalize(""))
-        .expect("no luck with the char count");
-    if let Some(usizes) = noah.root.translator.translate(word) {
-        if !cc.add(usizes) {
-            dictionary_error(word, &noah.root)
+    let saved = anagrammer.save_memo().unwrap_or_else(|e| fail(e, &options));
+    if options.is_present("stats") {
+        let stats = anagrammer.cache_stats();
+        eprintln!(
+            "cache: {} entries, {} bytes, {} hits, {} misses, {} evictions",
+            stats.entries, stats.bytes, stats.hits, stats.misses, stats.evictions
+        );
+        let pruned = anagrammer.prune_stats();
+        if pruned.dead > 0 {
+            eprintln!(
+                "pruning: {} dead residuals, {} partial anagrams pruned",
+                pruned.dead, pruned.pruned
+            );
         }
-    } else {
-        dictionary_error(word, &noah.root)
-    }
-    cc.set_limits();
-    let materials = vec![ToDo::seed(cc)];
-    let (messages, _) = factory::manufacture(threads, 3, materials, noah.clone());
-    let mut words = vec![];
-    for m in messages {
-        if let Some(todo) = m {
-            for word in todo.words() {
-                words.push(noah.root.translator.etalsnart(&word).unwrap());
-            }
-        } else {
-            break;
+        if options.is_present("memo") {
+            eprintln!(
+                "memo: {} entries restored, {} saved",
+                anagrammer.restored(),
+                saved
+            );
         }
     }
-    words
 }
 
-fn dictionary_error(word: &str, trie: &Trie) -> ! {
-    let (good, bad) = trie.translator.unfamiliar_character(word);
+// compile the dictionary into a file that may be loaded quickly
+fn compile(compile_options: &ArgMatches) -> Result<(), RanaError> {
+    let source = match compile_options.value_of("dictionary") {
+        Some(source) => source,
+        None => return Err(RanaError::invalid("dictionary", "none provided")),
+    };
+    let target = match compile_options.value_of("output") {
+        Some(target) => target.to_string(),
+        None => dictionary::compiled_path(source),
+    };
+    let (translator, arena) =
+        dictionary::read_text(source).map_err(|e| RanaError::UnreadableDictionary {
+            path: source.to_string(),
+            source: e,
+        })?;
+    dictionary::write(&target, &translator, &arena).map_err(|e| RanaError::UnwritableFile {
+        path: target.clone(),
+        source: e,
+    })?;
+    let stats = arena.stats();
     eprintln!(
-        "character in {} not present in any word in dictionary:\n\n\t{}-->{}",
-        word, good, bad
+        "compiled {} to {}\n\ttrie: {} nodes, {} bytes\n\tminimized: {} nodes, {} bytes",
+        source, target, stats.trie_nodes, stats.trie_bytes, stats.nodes, stats.bytes
     );
-    process::exit(1)
+    Ok(())
 }
 
-fn make_trie(opts: &ArgMatches, minimum_word_length: usize) -> Trie {
-    let mut file = match File::open(opts.value_of("dictionary").unwrap()) {
-        Err(_) => {
-            eprintln!("could not read dictionary:\n\n{}", opts.usage());
-            process::exit(1)
-        }
-        Ok(file) => file,
-    };
-    let mut strings = String::new();
-    match file.read_to_string(&mut strings) {
-        Err(why) => {
-            eprintln!(
-                "could not read words from dictionary: {}\n\n{}",
-                why,
-                opts.usage()
-            );
-            process::exit(1)
-        }
-        Ok(_) => (),
-    }
-    let words: Vec<&str> = strings
-        .lines()
-        .filter(|w| w.trim().len() >= minimum_word_length)
-        .collect();
-    let translator = Translator::new(normalize, words.iter().map(|s| *s));
-    let any_excluded = opts.is_present("exclude");
-    let mut excluded = HashSet::new();
-    if any_excluded {
-        for word in opts.values_of("exclude").unwrap() {
-            excluded.insert(translator.translate(word).unwrap());
-        }
-    }
-    let mut t = TrieNodeBuilder::new();
-    for word in words {
-        let translation = translator.translate(word).unwrap();
-        if any_excluded && excluded.contains(&translation) {
-            continue;
-        }
-        t.add(&translation);
-    }
-    let random = opts.is_present("random");
-    Trie::new(
-        t.build(),
-        translator,
-        !(opts.is_present("no_cache") || opts.is_present("set")),
-        random,
-    )
-}
-
-struct AnagramFun {
-    root: Trie,
+// parse the value of a numeric option
+fn number(options: &ArgMatches, name: &str, description: &str) -> Result<usize, RanaError> {
+    options
+        .value_of(name)
+        .unwrap()
+        .parse::<usize>()
+        .map_err(|why| RanaError::invalid(description, &why.to_string()))
 }
 
-impl WorkerFun<ToDo> for AnagramFun {
-    fn improve(&self, needs_work: ToDo) -> Vec<ToDo> {
-        let mut done = vec![];
-        let arc = Arc::new(needs_work);
-        for (word, cc) in self.root.words_for(arc.undone.clone(), &arc.word, &false) {
-            done.push(ToDo::new(arc.clone(), word, cc.clone()))
+// split a required match into its count, if it has one, and its pattern
+fn requirement_count(requirement: &str) -> Result<(usize, &str), RanaError> {
+    if let Some(i) = requirement.find(':') {
+        let count = &requirement[..i];
+        if !count.is_empty() && count.chars().all(|c| c.is_ascii_digit()) {
+            let count = count
+                .parse::<usize>()
+                .map_err(|why| RanaError::invalid("required match", &why.to_string()))?;
+            return Ok((count, &requirement[i + 1..]));
         }
-        done
-    }
-    fn inspect(&self, thing: &ToDo) -> bool {
-        thing.done()
     }
+    Ok((1, requirement))
+}
+
+// report the error and exit with the code appropriate to it
+fn fail(error: RanaError, options: &ArgMatches) -> ! {
+    eprintln!("{}\n\n{}", error, options.usage());
+    process::exit(error.exit_code())
 }
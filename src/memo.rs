@@ -0,0 +1,282 @@
+//! saving the cache between runs
+//!
+//! The words extractable from a residual `CharCount` depend only on the
+//! dictionary and its letter order, the words excluded from it, the word
+//! length limits, the forbidden patterns, and whether letters may be left
+//! over, not on the phrase. A memo file saves cache entries so later runs
+//! with the same configuration needn't recompute them. Its layout, all integers
+//! little-endian, is
+//!
+//! ```text
+//! magic     b"rmem"
+//! version   u32
+//! key       u64, identifying the dictionary and configuration
+//! records   until the end of the file
+//! ```
+//!
+//! A record is a u32 length, that many bytes of payload, and a u64 FNV-1a
+//! checksum of the payload. The payload is a sequence of unsigned LEB128
+//! numbers:
+//!
+//! ```text
+//! residual  a count of characters followed by that many pairs of
+//!           character and count, where the character one past the end
+//!           of the alphabet stands for blanks
+//! words     a count of words followed by that many words, where a word is
+//!           a length followed by that many characters
+//! ```
+//!
+//! Records are only ever appended, so a run interrupted while saving leaves
+//! a damaged record at the end. This and anything after it is ignored and
+//! overwritten by the next save.
+
+use dictionary::{checksum, fingerprint};
+use regex::Regex;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::sync::Arc;
+use trie::Trie;
+use util::CharCount;
+
+const MAGIC: &[u8; 4] = b"rmem";
+const VERSION: u32 = 2;
+const HEADER_LENGTH: usize = 16;
+
+/// A memo file and the part of it known to be good.
+#[derive(Debug, Clone)]
+pub struct Memo {
+    path: String,
+    key: u64,
+    // the length of the undamaged part of the file, if it belongs to this key
+    valid: Option<u64>,
+}
+
+impl Memo {
+    /// Identifies the memo for the given trie, from which the excluded words
+    /// have been removed, word length limits, and forbidden patterns. If
+    /// anagrams may leave letters unused, the cache keeps the words for
+    /// residuals it would otherwise give none, so its entries differ.
+    pub fn new(
+        path: &str,
+        trie: &Trie,
+        minimum_word_length: usize,
+        maximum_word_length: Option<usize>,
+        forbidden: &[Regex],
+        partial: bool,
+    ) -> Memo {
+        let mut key = fingerprint(&trie.translator, &trie.arena)
+            .to_le_bytes()
+            .to_vec();
+        key.extend_from_slice(&(minimum_word_length as u64).to_le_bytes());
+        // a presence byte keeps a maximum length from reading as the length
+        // of a forbidden pattern
+        match maximum_word_length {
+            Some(n) => {
+                key.push(1);
+                key.extend_from_slice(&(n as u64).to_le_bytes());
+            }
+            None => key.push(0),
+        }
+        for re in forbidden {
+            key.extend_from_slice(&(re.as_str().len() as u64).to_le_bytes());
+            key.extend_from_slice(re.as_str().as_bytes());
+        }
+        if partial {
+            key.push(1);
+        }
+        Memo {
+            path: path.to_string(),
+            key: checksum(&key),
+            valid: None,
+        }
+    }
+    /// The memo file.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+    /// Restores to the cache the saved entries whose residuals fit within the
+    /// given character count, returning how many were restored. A missing
+    /// file, or one for another dictionary or configuration, holds no
+    /// entries.
+    pub fn load(&mut self, cc: &CharCount, trie: &Trie) -> io::Result<usize> {
+        let mut bytes = vec![];
+        match File::open(&self.path) {
+            Ok(mut file) => file.read_to_end(&mut bytes)?,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e),
+        };
+        if bytes.len() < HEADER_LENGTH
+            || !bytes.starts_with(MAGIC)
+            || bytes[4..8] != VERSION.to_le_bytes()
+            || bytes[8..16] != self.key.to_le_bytes()
+        {
+            return Ok(0);
+        }
+        let mut offset = HEADER_LENGTH;
+        let mut restored = 0;
+        while let Some((payload, end)) = record(&bytes, offset) {
+            // a record that passes its checksum but cannot be decoded was
+            // written by something else; stop trusting the file there
+            match decode(payload, cc, trie) {
+                Some(Some((residual, list))) => {
+                    trie.cache.restore(Arc::new(residual), Arc::new(list));
+                    restored += 1;
+                }
+                Some(None) => (),
+                None => break,
+            }
+            offset = end;
+        }
+        self.valid = Some(offset as u64);
+        Ok(restored)
+    }
+    /// Appends the cache's unsaved entries to the file, returning how many
+    /// were saved. If the file belongs to another dictionary or
+    /// configuration, it is replaced.
+    pub fn save(&mut self, trie: &Trie) -> io::Result<usize> {
+        let entries = trie.cache.take_unsaved();
+        let mut bytes = vec![];
+        let mut file = match self.valid {
+            Some(length) => {
+                let mut file = OpenOptions::new().write(true).open(&self.path)?;
+                // discard any damaged records
+                file.set_len(length)?;
+                file.seek(SeekFrom::End(0))?;
+                file
+            }
+            None => {
+                bytes.extend_from_slice(MAGIC);
+                bytes.extend_from_slice(&VERSION.to_le_bytes());
+                bytes.extend_from_slice(&self.key.to_le_bytes());
+                File::create(&self.path)?
+            }
+        };
+        for (residual, list) in &entries {
+            let mut payload = vec![];
+            let mut characters: Vec<(usize, usize)> = (0..residual.alphabet_size())
+                .map(|c| (c, residual.count(c)))
+                .filter(|&(_, n)| n > 0)
+                .collect();
+            if residual.blanks > 0 {
+                characters.push((residual.alphabet_size(), residual.blanks));
+            }
+            push(&mut payload, characters.len());
+            for (c, n) in characters {
+                push(&mut payload, c);
+                push(&mut payload, n);
+            }
+            push(&mut payload, list.len());
+            for (word, _) in list.iter() {
+                push(&mut payload, word.len());
+                for &c in word.iter() {
+                    push(&mut payload, c);
+                }
+            }
+            bytes.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(&payload);
+            bytes.extend_from_slice(&checksum(&payload).to_le_bytes());
+        }
+        file.write_all(&bytes)?;
+        self.valid = Some(file.stream_position()?);
+        Ok(entries.len())
+    }
+}
+
+// the payload of the record at the offset and the offset after it, if the
+// record is whole and undamaged
+fn record(bytes: &[u8], offset: usize) -> Option<(&[u8], usize)> {
+    let mut n = [0; 4];
+    n.copy_from_slice(bytes.get(offset..offset + 4)?);
+    let start = offset + 4;
+    let end = start + u32::from_le_bytes(n) as usize;
+    let payload = bytes.get(start..end)?;
+    let mut sum = [0; 8];
+    sum.copy_from_slice(bytes.get(end..end + 8)?);
+    if u64::from_le_bytes(sum) == checksum(payload) {
+        Some((payload, end + 8))
+    } else {
+        None
+    }
+}
+
+type Entry = (CharCount, Vec<(Arc<Vec<usize>>, Arc<CharCount>)>);
+
+// Decodes a record's payload. This is None if the payload is malformed and
+// Some(None) if the residual does not fit within the character count.
+fn decode(payload: &[u8], cc: &CharCount, trie: &Trie) -> Option<Option<Entry>> {
+    let mut reader = Reader { payload, offset: 0 };
+    let alphabet_size = cc.alphabet_size();
+    let mut residual = trie.translator.count("").unwrap();
+    for _ in 0..reader.number()? {
+        let c = reader.number()?;
+        let n = reader.number()?;
+        if c == alphabet_size {
+            if n == 0 || residual.blanks > 0 {
+                return None;
+            }
+            if cc.blanks < n {
+                return Some(None);
+            }
+            residual.add_blanks(n);
+            continue;
+        }
+        if c >= alphabet_size || n == 0 || residual.count(c) > 0 {
+            return None;
+        }
+        if cc.count(c) < n {
+            return Some(None);
+        }
+        residual.add(vec![c; n]);
+    }
+    residual.set_limits();
+    let mut list = vec![];
+    for _ in 0..reader.number()? {
+        let mut word = vec![];
+        let mut remainder = residual.clone();
+        for _ in 0..reader.number()? {
+            let c = reader.number()?;
+            if c >= alphabet_size || remainder.take(c).is_none() {
+                return None;
+            }
+            word.push(c);
+        }
+        list.push((Arc::new(word), Arc::new(remainder)));
+    }
+    if reader.offset != payload.len() {
+        return None;
+    }
+    Some(Some((residual, list)))
+}
+
+// append an unsigned LEB128 number
+fn push(bytes: &mut Vec<u8>, mut n: usize) {
+    while n >= 0x80 {
+        bytes.push((n as u8) | 0x80);
+        n >>= 7;
+    }
+    bytes.push(n as u8);
+}
+
+struct Reader<'a> {
+    payload: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn number(&mut self) -> Option<usize> {
+        let mut n: usize = 0;
+        let mut shift = 0;
+        loop {
+            let b = *self.payload.get(self.offset)?;
+            self.offset += 1;
+            if shift >= 64 {
+                return None;
+            }
+            n |= ((b & 0x7f) as usize) << shift;
+            if b & 0x80 == 0 {
+                return Some(n);
+            }
+            shift += 7;
+        }
+    }
+}
@@ -0,0 +1,73 @@
+//! orders in which anagrams may be listed
+
+use anagrammer::Anagram;
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+/// An order in which to list anagrams whose words are each in alphabetical
+/// order. Whatever the order, anagrams leaving fewer letters unused come
+/// first, and anagrams otherwise equal are in lexicographic order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Order {
+    /// by their first words, then their second, and so on
+    #[default]
+    Lexicographic,
+    /// those with fewer words first
+    FewestWords,
+    /// those with longer longest words first
+    LongestWord,
+}
+
+impl Order {
+    /// The names by which orders are parsed, in the order of the variants.
+    pub const NAMES: &'static [&'static str] = &["lexicographic", "fewest-words", "longest-word"];
+    /// Compares two anagrams. Letters blanks stand for sort as though they
+    /// were not.
+    pub fn compare(self, a: &Anagram, b: &Anagram) -> Ordering {
+        let words = |anagram: &Anagram| -> Vec<String> {
+            anagram.words.iter().map(|w| w.to_lowercase()).collect()
+        };
+        let longest = |anagram: &Anagram| -> usize {
+            anagram
+                .words
+                .iter()
+                .map(|w| w.chars().count())
+                .max()
+                .unwrap_or(0)
+        };
+        let first = a.leftover.len().cmp(&b.leftover.len());
+        let second = match self {
+            Order::Lexicographic => Ordering::Equal,
+            Order::FewestWords => a.words.len().cmp(&b.words.len()),
+            Order::LongestWord => longest(b).cmp(&longest(a)),
+        };
+        first
+            .then(second)
+            .then_with(|| words(a).cmp(&words(b)))
+            .then_with(|| a.leftover.cmp(&b.leftover))
+    }
+}
+
+impl FromStr for Order {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Order, String> {
+        match s {
+            "lexicographic" => Ok(Order::Lexicographic),
+            "fewest-words" => Ok(Order::FewestWords),
+            "longest-word" => Ok(Order::LongestWord),
+            _ => Err(format!("{} is not one of {}", s, Order::NAMES.join(", "))),
+        }
+    }
+}
+
+impl fmt::Display for Order {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Order::Lexicographic => Order::NAMES[0],
+            Order::FewestWords => Order::NAMES[1],
+            Order::LongestWord => Order::NAMES[2],
+        };
+        write!(f, "{}", name)
+    }
+}
@@ -0,0 +1,81 @@
+//! remembering which residuals can never become anagrams
+//!
+//! A residual `CharCount` may be reached by many partial anagrams, each
+//! taking the same words from the phrase in a different order. If no words
+//! complete it, the search would otherwise rediscover this each time. The
+//! search tells a `Pruner` whenever it has tried every way of completing a
+//! partial anagram and found none, and consults it before working on any
+//! partial anagram. A residual whose words must sort at or after some key
+//! is no more completable with a later key, so the `Pruner` keeps for each
+//! residual only the earliest key with which it is known to be dead.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use util::{CharCount, CharCountMap};
+
+/// A record of the residuals known never to complete, shared by the workers
+/// of a search.
+pub struct Pruner {
+    // for each dead residual, the earliest sort key with which it is dead
+    memo: RwLock<CharCountMap<Arc<Vec<usize>>>>,
+    pruned: AtomicUsize,
+}
+
+/// A summary of a `Pruner`'s work.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub struct PruneStats {
+    /// residuals found never to complete
+    pub dead: usize,
+    /// partial anagrams abandoned because their residuals were dead
+    pub pruned: usize,
+}
+
+impl Default for Pruner {
+    fn default() -> Self {
+        Pruner::new()
+    }
+}
+
+impl Pruner {
+    /// A pruner knowing of no dead residuals.
+    pub fn new() -> Pruner {
+        Pruner {
+            memo: RwLock::new(CharCountMap::default()),
+            pruned: AtomicUsize::new(0),
+        }
+    }
+    /// Whether the `CharCount` might yet be used up by words sorting at or
+    /// after the key. If not, this is counted as a pruning.
+    pub fn viable(&self, cc: &CharCount, key: &[usize]) -> bool {
+        let dead = match self.memo.read().unwrap().get(cc) {
+            Some(earliest) => key >= &earliest[..],
+            None => false,
+        };
+        if dead {
+            self.pruned.fetch_add(1, Ordering::Relaxed);
+        }
+        !dead
+    }
+    /// Records that no words sorting at or after the key use up the
+    /// `CharCount`.
+    pub fn dead(&self, cc: &Arc<CharCount>, key: &[usize]) {
+        let mut memo = self.memo.write().unwrap();
+        match memo.get_mut(cc) {
+            Some(earliest) => {
+                if key < &earliest[..] {
+                    *earliest = Arc::new(key.to_vec());
+                }
+            }
+            None => {
+                memo.insert(cc.clone(), Arc::new(key.to_vec()));
+            }
+        }
+    }
+    /// The residuals found dead and the partial anagrams pruned so far.
+    pub fn stats(&self) -> PruneStats {
+        PruneStats {
+            dead: self.memo.read().unwrap().len(),
+            pruned: self.pruned.load(Ordering::Relaxed),
+        }
+    }
+}
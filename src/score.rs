@@ -0,0 +1,147 @@
+//! combining the weights of words into the score of an anagram
+
+use std::fmt;
+use std::str::FromStr;
+
+/// A way to combine the weights of an anagram's words into a score. Higher
+/// scores are better.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Combiner {
+    /// the total weight; this favors anagrams with many words
+    Sum,
+    /// the weight of the weakest word
+    Minimum,
+    /// the nth root of the product of the n weights; this favors anagrams
+    /// whose words are all good without regard to their number. Weights
+    /// should be positive; an anagram with a word whose weight is not scores 0.
+    #[default]
+    GeometricMean,
+}
+
+impl Combiner {
+    /// The names by which combiners are parsed, in the order of the variants.
+    pub const NAMES: &'static [&'static str] = &["sum", "min", "geometric-mean"];
+    /// Combines the weights. The score of no words at all is 0.
+    pub fn combine(self, weights: &[f32]) -> f64 {
+        if weights.is_empty() {
+            return 0.0;
+        }
+        match self {
+            Combiner::Sum => weights.iter().map(|&w| f64::from(w)).sum(),
+            Combiner::Minimum => weights
+                .iter()
+                .map(|&w| f64::from(w))
+                .fold(f64::INFINITY, f64::min),
+            Combiner::GeometricMean => {
+                if weights.iter().any(|&w| w <= 0.0) {
+                    return 0.0;
+                }
+                let logs: f64 = weights.iter().map(|&w| f64::from(w).ln()).sum();
+                (logs / weights.len() as f64).exp()
+            }
+        }
+    }
+}
+
+/// A running summary of the weights of the words found so far in an anagram
+/// under construction, from which its score may be bounded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tally {
+    words: usize,
+    sum: f64,
+    logs: f64,
+    minimum: f64,
+}
+
+impl Tally {
+    /// The tally of no words.
+    pub fn new() -> Tally {
+        Tally {
+            words: 0,
+            sum: 0.0,
+            logs: 0.0,
+            minimum: f64::INFINITY,
+        }
+    }
+    /// The tally with another word of the given weight.
+    pub fn add(&self, weight: f32) -> Tally {
+        let weight = f64::from(weight);
+        Tally {
+            words: self.words + 1,
+            sum: self.sum + weight,
+            // the log of a weight which is not positive is never used
+            logs: self.logs + weight.max(f64::MIN_POSITIVE).ln(),
+            minimum: self.minimum.min(weight),
+        }
+    }
+    /// The score of the words tallied, as `Combiner::combine` would give it.
+    pub fn score(&self, combiner: Combiner) -> f64 {
+        if self.words == 0 {
+            return 0.0;
+        }
+        match combiner {
+            Combiner::Sum => self.sum,
+            Combiner::Minimum => self.minimum,
+            Combiner::GeometricMean => {
+                if self.minimum <= 0.0 {
+                    0.0
+                } else {
+                    (self.logs / self.words as f64).exp()
+                }
+            }
+        }
+    }
+    /// An upper bound on the score once between 1 and `most` more words are
+    /// added, none weighing more than `best`.
+    pub fn bound(&self, combiner: Combiner, best: f32, most: usize) -> f64 {
+        let best = f64::from(best);
+        let most = most.max(1) as f64;
+        match combiner {
+            Combiner::Sum => self.sum + if best > 0.0 { best * most } else { best },
+            Combiner::Minimum => self.minimum.min(best),
+            Combiner::GeometricMean => {
+                if self.minimum <= 0.0 || best <= 0.0 {
+                    return 0.0;
+                }
+                // the mean moves monotonically toward best as words are
+                // added, so it is greatest with either the fewest or the most
+                let n = self.words as f64;
+                let mean = |m: f64| ((self.logs + m * best.ln()) / (n + m)).exp();
+                mean(1.0).max(mean(most))
+            }
+        }
+    }
+}
+
+impl Default for Tally {
+    fn default() -> Tally {
+        Tally::new()
+    }
+}
+
+impl FromStr for Combiner {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Combiner, String> {
+        match s {
+            "sum" => Ok(Combiner::Sum),
+            "min" => Ok(Combiner::Minimum),
+            "geometric-mean" => Ok(Combiner::GeometricMean),
+            _ => Err(format!(
+                "{} is not one of {}",
+                s,
+                Combiner::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Combiner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Combiner::Sum => Combiner::NAMES[0],
+            Combiner::Minimum => Combiner::NAMES[1],
+            Combiner::GeometricMean => Combiner::NAMES[2],
+        };
+        write!(f, "{}", name)
+    }
+}
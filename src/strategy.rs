@@ -0,0 +1,49 @@
+//! ways of searching for each anagram only once
+
+use std::fmt;
+use std::str::FromStr;
+
+/// How the search avoids finding the same anagram with its words in
+/// different orders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Strategy {
+    /// each word must sort at or after the word before it
+    #[default]
+    SortKey,
+    /// each word must contain the rarest letter left; a word need only sort
+    /// at or after the word before it if that word contained the same rarest
+    /// letter. This has fewer words to try at each step, but it cannot leave
+    /// letters unused, so it cannot be combined with blanks or leftovers.
+    RarestLetter,
+}
+
+impl Strategy {
+    /// The names by which strategies are parsed, in the order of the
+    /// variants.
+    pub const NAMES: &'static [&'static str] = &["sort-key", "rarest-letter"];
+}
+
+impl FromStr for Strategy {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Strategy, String> {
+        match s {
+            "sort-key" => Ok(Strategy::SortKey),
+            "rarest-letter" => Ok(Strategy::RarestLetter),
+            _ => Err(format!(
+                "{} is not one of {}",
+                s,
+                Strategy::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Strategy::SortKey => Strategy::NAMES[0],
+            Strategy::RarestLetter => Strategy::NAMES[1],
+        };
+        write!(f, "{}", name)
+    }
+}
@@ -0,0 +1,150 @@
+//! word length templates such as crossword enumerations
+
+use std::fmt;
+use std::str::FromStr;
+
+/// The lengths allowed the word in one position of a `Template`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Slot {
+    /// the fewest letters the word may have
+    pub shortest: usize,
+    /// the most letters the word may have, if there is a limit
+    pub longest: Option<usize>,
+}
+
+impl Slot {
+    /// Whether a word of this length fits the slot.
+    // is_none_or is too new for the compilers rana still builds with
+    #[allow(clippy::unnecessary_map_or)]
+    pub fn fits(&self, length: usize) -> bool {
+        length >= self.shortest && self.longest.map_or(true, |n| length <= n)
+    }
+}
+
+/// A sequence of slots, one per word, which anagrams must fill in order.
+///
+/// A template is written as its slots separated by spaces or commas, and
+/// may be wrapped in parentheses, so "5 3 _" and "(5,3,6)" are both
+/// templates. A slot is a length such as `5`, a range such as `3-5`, a
+/// minimum such as `4-`, or `_` for a word of any length.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    slots: Vec<Slot>,
+    // the fewest and most letters the slots from each one on can hold
+    least: Vec<usize>,
+    most: Vec<Option<usize>>,
+}
+
+impl Template {
+    /// Makes a template of the given slots.
+    pub fn new(slots: Vec<Slot>) -> Template {
+        let mut least = vec![0; slots.len() + 1];
+        let mut most = vec![Some(0); slots.len() + 1];
+        for (i, slot) in slots.iter().enumerate().rev() {
+            least[i] = least[i + 1] + slot.shortest;
+            most[i] = match (most[i + 1], slot.longest) {
+                (Some(a), Some(b)) => Some(a + b),
+                _ => None,
+            };
+        }
+        Template { slots, least, most }
+    }
+    /// The slots in order.
+    pub fn slots(&self) -> &[Slot] {
+        &self.slots
+    }
+    /// Whether the given number of letters could fill the slots from the
+    /// given one on.
+    // is_none_or is too new for the compilers rana still builds with
+    #[allow(clippy::unnecessary_map_or)]
+    pub fn room(&self, slot: usize, letters: usize) -> bool {
+        slot <= self.slots.len()
+            && letters >= self.least[slot]
+            && self.most[slot].map_or(true, |n| letters <= n)
+    }
+    /// The length of the shortest word any slot allows.
+    pub fn shortest(&self) -> usize {
+        self.slots.iter().map(|s| s.shortest).min().unwrap_or(0)
+    }
+    /// The length of the longest word any slot allows, if every slot has a
+    /// limit.
+    pub fn longest(&self) -> Option<usize> {
+        self.slots
+            .iter()
+            .map(|s| s.longest)
+            .try_fold(0, |m, n| n.map(|n| m.max(n)))
+    }
+}
+
+impl FromStr for Template {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Template, String> {
+        let trimmed = s.trim();
+        let inner = if trimmed.starts_with('(') && trimmed.ends_with(')') {
+            &trimmed[1..trimmed.len() - 1]
+        } else {
+            trimmed
+        };
+        let mut slots = vec![];
+        for token in inner.split(|c: char| c == ',' || c.is_whitespace()) {
+            if !token.is_empty() {
+                slots.push(slot(token)?);
+            }
+        }
+        if slots.is_empty() {
+            return Err(format!("{:?} has no slots", s));
+        }
+        Ok(Template::new(slots))
+    }
+}
+
+// parse a single slot
+fn slot(token: &str) -> Result<Slot, String> {
+    let number = |n: &str| match n.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!("{} is not a positive length", n)),
+    };
+    if token == "_" {
+        return Ok(Slot {
+            shortest: 1,
+            longest: None,
+        });
+    }
+    let (shortest, longest) = match token.find('-') {
+        Some(i) => {
+            let longest = &token[i + 1..];
+            let longest = if longest.is_empty() {
+                None
+            } else {
+                Some(number(longest)?)
+            };
+            (number(&token[..i])?, longest)
+        }
+        None => {
+            let n = number(token)?;
+            (n, Some(n))
+        }
+    };
+    if longest.is_some_and(|n| n < shortest) {
+        return Err(format!("{} is an empty range", token));
+    }
+    Ok(Slot { shortest, longest })
+}
+
+impl fmt::Display for Slot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.longest {
+            Some(n) if n == self.shortest => write!(f, "{}", n),
+            Some(n) => write!(f, "{}-{}", self.shortest, n),
+            None if self.shortest == 1 => write!(f, "_"),
+            None => write!(f, "{}-", self.shortest),
+        }
+    }
+}
+
+impl fmt::Display for Template {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let slots: Vec<String> = self.slots.iter().map(|s| s.to_string()).collect();
+        write!(f, "{}", slots.join(" "))
+    }
+}
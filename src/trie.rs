@@ -1,52 +1,75 @@
 //! efficient representation of word lists
 
-// use rand::rngs::StdRng;
+use cache::Cache;
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
-use rand::thread_rng;
+use rand::{thread_rng, SeedableRng};
+use regex::Regex;
 use std::cmp::Ordering;
-use std::collections::HashMap;
-use std::mem::size_of;
-use std::sync::{Arc, RwLock};
-use util::{CharCount, CharSet, ToDo, Translator};
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::mem::{size_of, size_of_val};
+use std::sync::Arc;
+use util::{CharCount, CharCountHasher, CharSet, ToDo, Translator};
 
-/// The magical boundary between words and numbers, a `Trie` wraps a `TrieNode`
+/// The words extractable from a `CharCount`, in their numeric representation,
+/// each paired with the residual `CharCount` remaining after its extraction.
+pub type WordList = Arc<Vec<(Arc<Vec<usize>>, Arc<CharCount>)>>;
+
+/// Some of the words of a `WordList`, given by their offsets into it, so
+/// they may be handed on without copying them or their residuals.
+#[derive(Debug, Clone, Default)]
+pub struct Extensions {
+    pub list: WordList,
+    pub offsets: Vec<u32>,
+}
+
+impl Extensions {
+    /// The words with their residuals, in the order of the offsets.
+    pub fn iter(&self) -> impl Iterator<Item = &(Arc<Vec<usize>>, Arc<CharCount>)> {
+        self.offsets.iter().map(move |&i| &self.list[i as usize])
+    }
+    /// Keeps only the words for which the predicate holds.
+    pub fn retain<F: FnMut(&[usize]) -> bool>(&mut self, mut f: F) {
+        let list = &self.list;
+        self.offsets.retain(|&i| f(&list[i as usize].0));
+    }
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+}
+
+/// The magical boundary between words and numbers, a `Trie` wraps a `TrieArena`
 /// and various things used for stringification, destringification, and various
 /// caches and denormalizations.
 pub struct Trie {
-    pub root: TrieNode,
+    pub arena: TrieArena,
     pub translator: Translator,
-    pub cache: RwLock<HashMap<Arc<CharCount>, Arc<Vec<(Arc<Vec<usize>>, Arc<CharCount>)>>>>,
+    pub cache: Cache,
     pub use_cache: bool,
     pub shuffle: bool,
-    empty_list: Arc<Vec<(Arc<Vec<usize>>, Arc<CharCount>)>>,
-    powers_of_ten: Vec<u128>,
+    /// if shuffling is seeded, the seed from which each list's shuffle derives
+    pub seed: Option<u64>,
+    empty_list: WordList,
+    // the words, shared by every list they are found in
+    words: Arc<HashSet<Arc<Vec<usize>>>>,
 }
 
 impl Trie {
-    pub fn new(root: TrieNode, translator: Translator, use_cache: bool, shuffle: bool) -> Trie {
-        let powers_of_ten = if use_cache {
-            let n = translator.alphabet_size();
-            if n > 38 {
-                panic!("the cache only works with alphabets of 38 characters or fewer")
-            }
-            let mut powers_of_ten = Vec::with_capacity(n);
-            let mut p: u128 = 1;
-            for _ in 0..n {
-                powers_of_ten.push(p);
-                p = p * 10;
-            }
-            powers_of_ten
-        } else {
-            Vec::with_capacity(0)
-        };
+    /// Wraps a `TrieArena`.
+    pub fn new(arena: TrieArena, translator: Translator, use_cache: bool, shuffle: bool) -> Trie {
         Trie {
-            root,
+            arena,
             translator,
             use_cache,
             shuffle,
-            cache: RwLock::new(HashMap::new()),
+            seed: None,
+            cache: Cache::new(None),
             empty_list: Arc::new(Vec::with_capacity(0)),
-            powers_of_ten: powers_of_ten,
+            words: Arc::new(HashSet::new()),
         }
     }
     // for comparing two sort keys
@@ -68,8 +91,8 @@ impl Trie {
             Ordering::Equal
         }
     }
-    fn index(key: &[usize], sorted_list: &Arc<Vec<(Arc<Vec<usize>>, Arc<CharCount>)>>) -> usize {
-        if sorted_list.len() == 0 {
+    fn index(key: &[usize], sorted_list: &WordList) -> usize {
+        if sorted_list.is_empty() {
             0
         } else {
             let mut start = 0;
@@ -96,85 +119,131 @@ impl Trie {
     }
     /// Removes the given word from the trie
     pub fn remove(&mut self, word: &[usize]) {
-        let n = self.root.clone().remove(word);
-        self.root = if let Some(n) = n {
-            n
-        } else {
-            TrieNodeBuilder::new().build()
-        };
+        self.arena.remove(word);
     }
     /// Produces the words, in their numeric representation, extractable from
     /// a `CharCount` along with the residual `CharCount`s remaining after their
     /// extraction. More precisely, it is those words sorting at or above the
     /// order of the given sort key. The sort key ensures that only one
-    /// permutation of a given anagram is produced.
+    /// permutation of a given anagram is produced. Blanks in the `CharCount`
+    /// stand in for whatever characters it lacks.
     pub fn words_for(
         &self,
         cc: Arc<CharCount>,
         sort_key: &[usize],
         all_words: &bool,
     ) -> Vec<(Arc<Vec<usize>>, Arc<CharCount>)> {
+        self.extensions_for(cc, sort_key, all_words)
+            .iter()
+            .cloned()
+            .collect()
+    }
+    /// The words `words_for` produces, as offsets into the cached list they
+    /// are found in, rather than copies.
+    pub fn extensions_for(
+        &self,
+        cc: Arc<CharCount>,
+        sort_key: &[usize],
+        all_words: &bool,
+    ) -> Extensions {
         let list = if self.use_cache {
-            let hashed = if !cc.hashed() {
-                let ref mut mutable = cc.clone();
-                let mut hashed = Arc::make_mut(mutable).clone();
-                hashed.calculate_hash(&self.powers_of_ten);
-                Arc::new(hashed)
-            } else {
-                cc.clone()
-            };
-            let cached = {
-                let map = self.cache.read().unwrap();
-                map.get(&hashed).map(Arc::clone)
-            };
-            if let Some(list) = cached {
-                list.clone()
-            } else {
-                let list = self.non_caching_words_for(&cc, sort_key, all_words);
-                {
-                    let mut map = self.cache.write().unwrap();
-                    map.insert(hashed, list.clone());
-                }
+            if let Some(list) = self.cache.get(&cc) {
+                list
+            } else if self.cache.worth_filling() {
+                let list = self.non_caching_words_for(&cc, sort_key, all_words, false);
+                self.remember(&cc, &list);
                 list
+            } else {
+                // the cache is thrashing, so only find the words needed now
+                self.non_caching_words_for(&cc, sort_key, all_words, true)
             }
         } else {
-            self.non_caching_words_for(&cc, sort_key, all_words)
+            self.non_caching_words_for(&cc, sort_key, all_words, true)
         };
-        let mut filtered = Vec::with_capacity(list.len());
-        for &(ref word, ref counts) in &list[Trie::index(sort_key, &list)..] {
-            filtered.push((word.clone(), counts.clone()));
-        }
+        let mut offsets =
+            (Trie::index(sort_key, &list) as u32..list.len() as u32).collect::<Vec<_>>();
         if self.shuffle {
-            let mut rng = thread_rng();
-            filtered.shuffle(&mut rng);
+            match self.seed {
+                Some(seed) => offsets.shuffle(&mut Trie::stream(seed, &cc, sort_key)),
+                None => offsets.shuffle(&mut thread_rng()),
+            }
+        }
+        Extensions { list, offsets }
+    }
+    /// A random number generator for shuffling the words for a `CharCount`
+    /// and sort key, the same for the same seed however the search reaches
+    /// them.
+    pub fn stream(seed: u64, cc: &CharCount, sort_key: &[usize]) -> StdRng {
+        let mut hasher = CharCountHasher::default();
+        hasher.write_u64(seed);
+        cc.hash(&mut hasher);
+        sort_key.hash(&mut hasher);
+        StdRng::seed_from_u64(hasher.finish())
+    }
+    /// Like `extensions_for`, but only the words containing the character
+    /// at offset `c`.
+    pub fn words_containing(
+        &self,
+        cc: Arc<CharCount>,
+        c: usize,
+        sort_key: &[usize],
+        all_words: &bool,
+    ) -> Extensions {
+        let mut words = self.extensions_for(cc, sort_key, all_words);
+        words.retain(|word| word.contains(&c));
+        words
+    }
+    /// All the words extractable from a `CharCount` with their residuals, as
+    /// `words_for` finds them for an empty sort key, but never shuffled, so
+    /// they are always sorted.
+    pub fn sorted_words_for(&self, cc: &Arc<CharCount>, all_words: &bool) -> WordList {
+        if !self.use_cache {
+            return self.non_caching_words_for(cc, &[], all_words, false);
+        }
+        if let Some(list) = self.cache.get(cc) {
+            return list;
+        }
+        let list = self.non_caching_words_for(cc, &[], all_words, false);
+        if self.cache.worth_filling() {
+            self.remember(cc, &list);
+        }
+        list
+    }
+    // caches the list unless it is empty only because some character has
+    // no use, which holds only for searches not wanting all the words; the
+    // cache is shared by every search, whatever words it wants
+    fn remember(&self, cc: &Arc<CharCount>, list: &WordList) {
+        if !Arc::ptr_eq(list, &self.empty_list) {
+            self.cache.insert(cc.clone(), list.clone());
         }
-        filtered
     }
-    // a repeated bit factored out of words_for (necessary after adding caching)
+    // a repeated bit factored out of words_for (necessary after adding caching);
+    // if sort is true, only words sorting at or above the sort key are found
     fn non_caching_words_for(
         &self,
         cc: &CharCount,
         sort_key: &[usize],
         all_words: &bool,
-    ) -> Arc<Vec<(Arc<Vec<usize>>, Arc<CharCount>)>> {
+        sort: bool,
+    ) -> WordList {
         let mut paired = vec![];
         let mut seed = Vec::with_capacity(cc.sum);
         let mut set = cc.to_set();
-        Trie::walk(
-            &self.root,
+        self.walk(
+            self.arena.root(),
             &mut seed,
             cc,
             &mut set,
             0,
             sort_key,
-            !self.use_cache,
+            sort,
             &mut paired,
         );
         if *all_words || set.is_empty() {
             Arc::new(
                 paired
                     .into_iter()
-                    .map(|(k, v)| (Arc::new(k), Arc::new(v)))
+                    .map(|(k, v)| (self.share(k), Arc::new(v)))
                     .collect(),
             )
         } else {
@@ -184,13 +253,54 @@ impl Trie {
             self.empty_list.clone()
         }
     }
-    // create a new Trie containing only the words present in the given character count
-    pub fn optimize(self, cc: CharCount) -> Trie {
-        let mut tnb = TrieNodeBuilder::new();
-        for (word, _) in self.words_for(Arc::new(cc), &Vec::with_capacity(0), &true) {
-            tnb.add(&word);
+    // the copy of the word every list holds, if the trie has one
+    fn share(&self, word: Vec<usize>) -> Arc<Vec<usize>> {
+        match self.words.get(&word) {
+            Some(shared) => shared.clone(),
+            None => Arc::new(word),
         }
-        Trie::new(tnb.build(), self.translator, self.use_cache, self.shuffle)
+    }
+    /// Create a new `Trie` containing only the words present in the given
+    /// character count whose lengths are at least the minimum and at most the
+    /// maximum, if any, and which match none of the forbidden patterns. Its
+    /// cache is empty but has the same budget as this one's.
+    pub fn optimize(
+        &self,
+        cc: &CharCount,
+        minimum_word_length: usize,
+        maximum_word_length: Option<usize>,
+        forbidden: &[Regex],
+    ) -> Trie {
+        let mut tnb = TrieArenaBuilder::new();
+        let longest = maximum_word_length.unwrap_or(usize::MAX);
+        let mut words = HashSet::new();
+        for (word, _) in self.words_for(Arc::new(cc.clone()), &[], &true) {
+            if word.len() < minimum_word_length || word.len() > longest {
+                continue;
+            }
+            if !forbidden.is_empty() {
+                let text = self.translator.etalsnart(&word).unwrap();
+                if forbidden.iter().any(|re| re.is_match(&text)) {
+                    continue;
+                }
+            }
+            if self.arena.weights().is_some() {
+                tnb.add_weighted(&word, self.arena.weight(&word));
+            } else {
+                tnb.add(&word);
+            }
+            words.insert(word);
+        }
+        let mut trie = Trie::new(
+            tnb.build(),
+            self.translator.clone(),
+            self.use_cache,
+            self.shuffle,
+        );
+        trie.seed = self.seed;
+        trie.words = Arc::new(words);
+        trie.cache = Cache::new(self.cache.budget());
+        trie
     }
     /// Convert a `ToDo` from a linked list of words in numeric representation
     /// to a single `String` representing an anagram.
@@ -208,9 +318,11 @@ impl Trie {
         s
     }
     // walk the trie, extending an extraction as far as possible from the given
-    // `TrieNode`
+    // node
+    #[allow(clippy::too_many_arguments)]
     fn walk(
-        node: &TrieNode,
+        &self,
+        node: usize,
         seed: &mut Vec<usize>,
         cc: &CharCount,
         set: &mut CharSet,
@@ -219,46 +331,66 @@ impl Trie {
         sort: bool,
         words: &mut Vec<(Vec<usize>, CharCount)>,
     ) {
-        if node.terminal && !seed.is_empty() {
+        let (terminal, children) = unsafe {
+            let n = self.arena.nodes.get_unchecked(node);
+            (
+                n.terminal,
+                self.arena
+                    .edges
+                    .get_unchecked(n.start as usize..n.end as usize),
+            )
+        };
+        if terminal && !seed.is_empty() {
             words.push((seed.clone(), cc.clone()));
-            set.remove(&seed);
+            set.remove(seed);
         }
         if cc.is_empty() {
             return;
         }
+        // blanks may stand for any character
+        let (first, last) = if cc.blanks > 0 {
+            (0, usize::MAX)
+        } else {
+            (cc.first, cc.last)
+        };
         let mut sorting = sort;
         let mut sort_char = 0;
         let start = if !sort || level >= sort_key.len() {
             sorting = false;
-            cc.first
+            first
         } else {
             sort_char = sort_key[level];
-            if sort_char < cc.first {
-                cc.first
+            if sort_char < first {
+                first
             } else {
                 sort_char
             }
         };
-        for c in start..cc.last {
-            if cc.has(c) {
-                if let Some(t) = node.get(c) {
-                    let mut characters_remaining = cc.clone();
-                    unsafe {
-                        characters_remaining.decrement(c);
-                    }
-                    seed.push(c);
-                    Trie::walk(
-                        t,
-                        seed,
-                        &characters_remaining,
-                        set,
-                        level + 1,
-                        &sort_key,
-                        sorting && (c == sort_char),
-                        words,
-                    );
-                    seed.pop();
-                }
+        // the edges are sorted by character
+        for edge in children {
+            let c = edge.character as usize;
+            if c < start {
+                continue;
+            }
+            if c >= last {
+                break;
+            }
+            if cc.has(c) || cc.blanks > 0 {
+                // a blank is used only if the character has run out
+                let mut characters_remaining = cc.clone();
+                characters_remaining.take(c);
+                seed.push(c);
+                self.walk(
+                    edge.target as usize,
+                    seed,
+                    &characters_remaining,
+                    set,
+                    level + 1,
+                    sort_key,
+                    sorting && (c == sort_char),
+                    words,
+                );
+                seed.pop();
             }
         }
     }
@@ -267,150 +399,402 @@ impl Trie {
 impl Clone for Trie {
     fn clone(&self) -> Self {
         Trie {
-            root: self.root.clone(),
+            arena: self.arena.clone(),
             translator: self.translator.clone(),
-            use_cache: self.use_cache.clone(),
-            shuffle: self.shuffle.clone(),
-            cache: RwLock::new(self.cache.read().unwrap().clone()),
+            use_cache: self.use_cache,
+            shuffle: self.shuffle,
+            seed: self.seed,
+            cache: self.cache.clone(),
             empty_list: self.empty_list.clone(),
-            powers_of_ten: self.powers_of_ten.clone(),
+            words: self.words.clone(),
         }
     }
 }
 
-/// A node in a trie (re`trie`val tree) representing a word list. A `TrieNode`
-/// contains a boolean indicating whether it is the end of a word and a list
-/// of child nodes representing possible continuations of the prefix represented
-/// by the node itself.
-#[derive(PartialEq, Debug, Clone)]
-pub struct TrieNode {
+/// A node in a `TrieArena`. Its outgoing edges are `edges[start..end]` in the
+/// arena's edge array.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub struct Node {
     pub terminal: bool,
-    pub children: Box<[Option<TrieNode>]>,
+    pub start: u32,
+    pub end: u32,
 }
 
-impl TrieNode {
-    pub fn contains(&self, word: &[usize]) -> bool {
-        if word.is_empty() {
-            self.terminal
-        } else {
-            if let Some(&Some(ref child)) = self.children.get(word[0]) {
-                child.contains(&word[1..])
-            } else {
-                false
-            }
+/// A transition from one `Node` in a `TrieArena` to another on a character.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub struct Edge {
+    /// the character in its numeric representation
+    pub character: u32,
+    /// the offset of the node reached in the arena's node array
+    pub target: u32,
+}
+
+/// A word list stored as a minimized directed acyclic word graph (DAWG) in
+/// two contiguous arrays, one of nodes and one of edges, which refer to one
+/// another by `u32` offsets. Common suffixes such as "-ing" are stored only
+/// once. Each node's edges are sorted by character, and so by the
+/// `Translator`'s numeric order. Children precede their parents, so the
+/// root is the last node. The arrays are shared, so cloning is cheap.
+///
+/// The words of a weighted word list share nodes no matter their weights,
+/// so the weights are kept apart, in the order of the words they belong to.
+/// A word's place in this order is found by counting the words preceding it
+/// along its path.
+#[derive(PartialEq, Debug, Clone)]
+pub struct TrieArena {
+    nodes: Arc<Vec<Node>>,
+    edges: Arc<Vec<Edge>>,
+    root: u32,
+    weights: Option<Arc<Weights>>,
+}
+
+/// The weight of any word in an unweighted word list, and of words without
+/// a weight in a weighted one.
+pub const DEFAULT_WEIGHT: f32 = 1.0;
+
+#[derive(PartialEq, Debug, Clone)]
+struct Weights {
+    // for each node, the number of words ending at or below it
+    words: Vec<u32>,
+    // the weight of each word, in sorted order
+    values: Vec<f32>,
+}
+
+/// The size of a word list both as a simple trie and as a minimized DAWG.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub struct TrieStats {
+    /// nodes in the equivalent trie with no shared suffixes
+    pub trie_nodes: usize,
+    /// bytes used by the equivalent trie with no shared suffixes
+    pub trie_bytes: usize,
+    /// distinct nodes in the DAWG
+    pub nodes: usize,
+    /// bytes used by the DAWG
+    pub bytes: usize,
+}
+
+impl TrieArena {
+    /// Assembles an arena from its parts. The last node is the root. Every
+    /// edge range must lie within `edges`, each node's edges must be sorted by
+    /// character, and every edge must point to an earlier node.
+    pub fn from_parts(nodes: Vec<Node>, edges: Vec<Edge>) -> TrieArena {
+        assert!(!nodes.is_empty(), "an arena needs a root");
+        let root = (nodes.len() - 1) as u32;
+        TrieArena {
+            nodes: Arc::new(nodes),
+            edges: Arc::new(edges),
+            root,
+            weights: None,
         }
     }
-    fn remove(mut self, word: &[usize]) -> Option<TrieNode> {
-        if !self.contains(word) {
-            Some(self)
-        } else {
-            if word.is_empty() {
-                if self.children.is_empty() {
-                    None
+    /// Gives the words weights, listed in the words' sorted order. This is
+    /// None if there are not as many weights as words.
+    pub fn weigh(mut self, values: Vec<f32>) -> Option<TrieArena> {
+        let mut words: Vec<u32> = Vec::with_capacity(self.nodes.len());
+        for node in self.nodes.iter() {
+            let below: u32 = self.edges[node.start as usize..node.end as usize]
+                .iter()
+                .map(|e| words[e.target as usize])
+                .sum();
+            words.push(node.terminal as u32 + below);
+        }
+        if words[self.root()] as usize != values.len() {
+            return None;
+        }
+        self.weights = Some(Arc::new(Weights { words, values }));
+        Some(self)
+    }
+    /// The weights of the words in their sorted order, if they have weights.
+    pub fn weights(&self) -> Option<&[f32]> {
+        self.weights.as_ref().map(|w| &w.values[..])
+    }
+    /// The weight of the given word. This is `DEFAULT_WEIGHT` if the words are
+    /// unweighted or the word is absent.
+    pub fn weight(&self, word: &[usize]) -> f32 {
+        match self.weights {
+            Some(ref weights) => match self.rank(word, weights) {
+                Some(rank) => weights.values[rank],
+                None => DEFAULT_WEIGHT,
+            },
+            None => DEFAULT_WEIGHT,
+        }
+    }
+    // the word's place in the sorted list of words
+    fn rank(&self, word: &[usize], weights: &Weights) -> Option<usize> {
+        let mut rank = 0;
+        let mut node = self.root();
+        for &c in word {
+            if self.nodes[node].terminal {
+                rank += 1;
+            }
+            let mut next = None;
+            for edge in self.children(node) {
+                if (edge.character as usize) < c {
+                    rank += weights.words[edge.target as usize] as usize;
                 } else {
-                    self.terminal = false;
-                    Some(self)
-                }
-            } else {
-                let i = word[0];
-                let mut v = self.children.to_vec();
-                let n = v.remove(i).unwrap().remove(&word[1..]);
-                if n.is_none() {
-                    if v.iter().all(|ref o| o.is_none()) {
-                        //
-                        if self.terminal {
-                            self.children = vec![].into_boxed_slice();
-                            Some(self)
-                        } else {
-                            None
-                        }
-                    } else {
-                        if i < v.len() {
-                            v.insert(i, n);
-                        }
-                        self.children = v.into_boxed_slice();
-                        Some(self)
+                    if edge.character as usize == c {
+                        next = Some(edge.target as usize);
                     }
-                } else {
-                    self.children[i] = n;
-                    Some(self)
+                    break;
                 }
             }
+            node = next?;
+        }
+        if self.nodes[node].terminal {
+            Some(rank)
+        } else {
+            None
         }
     }
-    pub fn size(&self) -> usize {
-        let mut s = 0;
-        s += size_of::<Box<[Option<TrieNode>]>>();
-        for c in self.children.iter() {
-            s += match c {
-                &Some(ref t) => {
-                    s += size_of::<Option<TrieNode>>();
-                    t.size()
+    /// All nodes, including any no longer reachable from the root after
+    /// removals.
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+    /// All edges, including any no longer reachable from the root after
+    /// removals.
+    pub fn edges(&self) -> &[Edge] {
+        &self.edges
+    }
+    /// The offset of the root node.
+    pub fn root(&self) -> usize {
+        self.root as usize
+    }
+    /// The edges leaving the given node.
+    pub fn children(&self, node: usize) -> &[Edge] {
+        let n = &self.nodes[node];
+        &self.edges[n.start as usize..n.end as usize]
+    }
+    // the node reached from the given node on the given character
+    fn get(&self, node: usize, c: usize) -> Option<usize> {
+        let children = self.children(node);
+        children
+            .binary_search_by_key(&(c as u32), |e| e.character)
+            .ok()
+            .map(|i| children[i].target as usize)
+    }
+    // the nodes along the path spelled by the word, starting with the root
+    fn path(&self, word: &[usize]) -> Option<Vec<usize>> {
+        let mut path = Vec::with_capacity(word.len() + 1);
+        let mut node = self.root();
+        path.push(node);
+        for &c in word {
+            node = self.get(node, c)?;
+            path.push(node);
+        }
+        Some(path)
+    }
+    pub fn contains(&self, word: &[usize]) -> bool {
+        match self.path(word) {
+            Some(path) => self.nodes[*path.last().unwrap()].terminal,
+            None => false,
+        }
+    }
+    /// Removes the given word. The nodes along the word's path are copied to
+    /// the end of the arena, leaving any other owners of the shared nodes and
+    /// of the arrays themselves undisturbed.
+    pub fn remove(&mut self, word: &[usize]) {
+        let path = match self.path(word) {
+            Some(ref path) if self.nodes[*path.last().unwrap()].terminal => path.clone(),
+            _ => return,
+        };
+        let rank = self.weights.as_ref().map(|w| self.rank(word, w).unwrap());
+        if let (Some(rank), Some(weights)) = (rank, self.weights.as_mut()) {
+            let weights = Arc::make_mut(weights);
+            weights.values.remove(rank);
+            // the copied nodes along the path each have one word fewer
+            for &old in path.iter().rev() {
+                let words = weights.words[old] - 1;
+                if words > 0 || old == path[0] {
+                    weights.words.push(words);
                 }
-                &None => size_of::<Option<TrieNode>>(),
             }
         }
-        s += size_of::<bool>();
-        s
+        let nodes = Arc::make_mut(&mut self.nodes);
+        let edges = Arc::make_mut(&mut self.edges);
+        // the replacement for the node below the current one, if any remains
+        let mut replacement: Option<u32> = None;
+        for (depth, &old) in path.iter().enumerate().rev() {
+            let node = nodes[old];
+            let mut children: Vec<Edge> = edges[node.start as usize..node.end as usize].to_vec();
+            let mut terminal = node.terminal;
+            if depth == word.len() {
+                terminal = false;
+            } else {
+                let c = word[depth] as u32;
+                let i = children.iter().position(|e| e.character == c).unwrap();
+                match replacement {
+                    Some(target) => children[i].target = target,
+                    None => {
+                        children.remove(i);
+                    }
+                }
+            }
+            replacement = if !terminal && children.is_empty() && depth > 0 {
+                None
+            } else {
+                let start = edges.len() as u32;
+                edges.extend(children);
+                nodes.push(Node {
+                    terminal,
+                    start,
+                    end: edges.len() as u32,
+                });
+                Some((nodes.len() - 1) as u32)
+            };
+        }
+        self.root = replacement.unwrap();
+    }
+    /// The number of bytes used by the nodes and edges reachable from the root.
+    pub fn size(&self) -> usize {
+        self.stats().bytes
     }
-    pub fn get(&self, i: usize) -> Option<&TrieNode> {
-        self.children.get(i).and_then(|o| o.as_ref())
+    /// Counts the nodes and bytes reachable from the root, and what they would
+    /// be if no suffixes were shared.
+    pub fn stats(&self) -> TrieStats {
+        let mut stats = TrieStats::default();
+        let mut seen = HashMap::new();
+        let nodes = self.tally(self.root(), &mut stats, &mut seen);
+        stats.trie_nodes = nodes;
+        // every node but the root is reached by one edge
+        stats.trie_bytes = nodes * size_of::<Node>() + (nodes - 1) * size_of::<Edge>();
+        stats
     }
+    // accumulate the DAWG statistics and return the trie's node count
+    fn tally(&self, node: usize, stats: &mut TrieStats, seen: &mut HashMap<usize, usize>) -> usize {
+        if let Some(&count) = seen.get(&node) {
+            return count;
+        }
+        stats.nodes += 1;
+        stats.bytes += size_of::<Node>() + size_of_val(self.children(node));
+        let mut count = 1;
+        for edge in self.children(node) {
+            count += self.tally(edge.target as usize, stats, seen);
+        }
+        seen.insert(node, count);
+        count
+    }
+}
+
+/// A disposable stage that collects the words for a `TrieArena`.
+#[derive(Clone, Default)]
+pub struct TrieArenaBuilder {
+    words: Vec<(Vec<usize>, f32)>,
+    weighted: bool,
 }
-/// A disposable stage that launches a `TrieNode`. `TrieNodeBuilder`s maintain
-/// a mutable state and functionality that are not necessary for a completed
-/// `TrieNode`.
-#[derive(Clone)]
-pub struct TrieNodeBuilder {
+
+// identifies a node by its terminality and its edges
+type Signature = (bool, Vec<Edge>);
+
+// a node whose edges are still being added during construction
+#[derive(Default)]
+struct Unfinished {
+    character: u32,
     terminal: bool,
-    children: Vec<Option<TrieNodeBuilder>>,
+    edges: Vec<Edge>,
 }
 
-impl TrieNodeBuilder {
-    /// Begins a builder for a `TrieNode`. The initial state of a
-    /// `TrieNodeBuilder` represents an empty, non-terminal `TrieNode`.
-    pub fn new() -> TrieNodeBuilder {
-        TrieNodeBuilder {
-            terminal: false,
-            children: vec![],
-        }
-    }
-    /// Recursively compiles a `TrieNode` representing the state of this
-    /// `TrieNodeBuilder` and its children.
-    pub fn build(self) -> TrieNode {
-        let children = self
-            .children
-            .into_iter()
-            .map(|opt| opt.and_then(|c| Some(c.build())))
-            .collect::<Vec<_>>()
-            .into_boxed_slice();
-        TrieNode {
-            terminal: self.terminal,
-            children: children,
-        }
-    }
-    fn get(&mut self, i: usize) -> &mut Option<TrieNodeBuilder> {
-        if i >= self.children.len() {
-            self.children.resize(i + 1, None);
-            unsafe {
-                let child = self.children.get_unchecked_mut(i);
-                *child = Some(TrieNodeBuilder::new());
-                child
-            }
-        } else if self.children.get(i).unwrap().is_none() {
-            let child = &mut self.children[i];
-            *child = Some(TrieNodeBuilder::new());
-            child
-        } else {
-            &mut self.children[i]
+impl TrieArenaBuilder {
+    /// Begins a builder for a `TrieArena`. The initial state of a
+    /// `TrieArenaBuilder` represents an empty word list.
+    pub fn new() -> TrieArenaBuilder {
+        TrieArenaBuilder {
+            words: vec![],
+            weighted: false,
         }
     }
     /// Adds a word to the trie.
     pub fn add(&mut self, word: &[usize]) {
-        if word.is_empty() {
-            self.terminal = true;
+        self.words.push((word.to_vec(), DEFAULT_WEIGHT));
+    }
+    /// Adds a word with a weight to the trie. If any word has a weight, the
+    /// trie is weighted, and words without one have the `DEFAULT_WEIGHT`. A
+    /// word added more than once keeps its greatest weight.
+    pub fn add_weighted(&mut self, word: &[usize], weight: f32) {
+        self.words.push((word.to_vec(), weight));
+        self.weighted = true;
+    }
+    /// Compiles the minimal `TrieArena` containing the words added.
+    ///
+    /// The words are added in sorted order, so when one word diverges from the
+    /// previous one the nodes below the point of divergence are complete and
+    /// may be merged with any equivalent node already in the arena. Children
+    /// are canonical by the time their parent is complete, so two nodes are
+    /// equivalent if they have the same terminality and identical edges.
+    pub fn build(mut self) -> TrieArena {
+        // sort by word and then by descending weight, so the first of each
+        // word has the greatest weight
+        self.words
+            .sort_by(|(a, x), (b, y)| a.cmp(b).then(y.partial_cmp(x).unwrap_or(Ordering::Equal)));
+        self.words.dedup_by(|(a, _), (b, _)| a == b);
+        let mut nodes = vec![];
+        let mut edges = vec![];
+        let mut registry = HashMap::new();
+        let mut unfinished = vec![Unfinished::default()];
+        let mut previous: &[usize] = &[];
+        for (word, _) in &self.words {
+            let common = previous
+                .iter()
+                .zip(word.iter())
+                .take_while(|(a, b)| a == b)
+                .count();
+            while unfinished.len() > common + 1 {
+                TrieArenaBuilder::finish(&mut unfinished, &mut nodes, &mut edges, &mut registry);
+            }
+            for &c in &word[common..] {
+                unfinished.push(Unfinished {
+                    character: c as u32,
+                    ..Unfinished::default()
+                });
+            }
+            unfinished.last_mut().unwrap().terminal = true;
+            previous = word;
+        }
+        while unfinished.len() > 1 {
+            TrieArenaBuilder::finish(&mut unfinished, &mut nodes, &mut edges, &mut registry);
+        }
+        // the root is never shared
+        let root = unfinished.pop().unwrap();
+        let start = edges.len() as u32;
+        edges.extend(root.edges);
+        nodes.push(Node {
+            terminal: root.terminal,
+            start,
+            end: edges.len() as u32,
+        });
+        let arena = TrieArena::from_parts(nodes, edges);
+        if self.weighted {
+            let weights = self.words.into_iter().map(|(_, weight)| weight).collect();
+            arena.weigh(weights).unwrap()
         } else {
-            self.get(word[0]).as_mut().unwrap().add(&word[1..]);
+            arena
         }
     }
+    // complete the deepest unfinished node and attach it to its parent
+    fn finish(
+        unfinished: &mut Vec<Unfinished>,
+        nodes: &mut Vec<Node>,
+        edges: &mut Vec<Edge>,
+        registry: &mut HashMap<Signature, u32>,
+    ) {
+        let node = unfinished.pop().unwrap();
+        let character = node.character;
+        let target = *registry
+            .entry((node.terminal, node.edges))
+            .or_insert_with_key(|(terminal, children)| {
+                let start = edges.len() as u32;
+                edges.extend_from_slice(children);
+                nodes.push(Node {
+                    terminal: *terminal,
+                    start,
+                    end: edges.len() as u32,
+                });
+                (nodes.len() - 1) as u32
+            });
+        unfinished
+            .last_mut()
+            .unwrap()
+            .edges
+            .push(Edge { character, target });
+    }
 }
@@ -1,10 +1,13 @@
 //! collection of structs mediating between words and tries and representing
 //! intermediate states in the discovery of anagrams
 
+use letter_order::LetterOrder;
 use std::cmp::{Eq, PartialEq};
 use std::collections::HashMap;
-use std::hash::{Hash, Hasher};
+use std::hash::{BuildHasherDefault, Hash, Hasher};
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
+use trie::WordList;
 
 /// Actually, there are currently no tests. The proof is in the pudding.
 #[cfg(test)]
@@ -13,17 +16,39 @@ mod tests {
     fn it_works() {}
 }
 
+/// The size of the largest alphabet whose `CharCount`s and `CharSet`s are
+/// kept inline, with a bit per character in a `u64`. Larger alphabets are
+/// counted in vectors.
+pub const PACKED_ALPHABET: usize = 64;
+
 /// A set-ish representation of the characters in a `CharCount`. A `CharSet`
 /// is a record of the *types* of characters present without regard to their
 /// count.
 #[derive(Debug, Clone)]
 pub struct CharSet {
-    pub chars: Vec<bool>,
-    count: usize,
+    members: Members,
+}
+
+#[derive(Debug, Clone)]
+enum Members {
+    // a bit per character present
+    Packed(u64),
+    Wide { chars: Vec<bool>, count: usize },
 }
 
 impl CharSet {
     pub fn new(chars: &[usize]) -> CharSet {
+        if chars.len() <= PACKED_ALPHABET {
+            let mut bits = 0;
+            for (i, &n) in chars.iter().enumerate() {
+                if n > 0 {
+                    bits |= 1 << i;
+                }
+            }
+            return CharSet {
+                members: Members::Packed(bits),
+            };
+        }
         let mut contained = vec![false; chars.len()];
         let mut count = 0;
         unsafe {
@@ -35,31 +60,41 @@ impl CharSet {
             }
         }
         CharSet {
-            chars: contained,
-            count,
+            members: Members::Wide {
+                chars: contained,
+                count,
+            },
         }
     }
     pub fn remove(&mut self, word: &[usize]) {
-        if !(word.is_empty() && self.is_empty()) {
-            for c in word {
-                unsafe {
-                    self.rm(*c);
+        match self.members {
+            Members::Packed(ref mut bits) => {
+                for &c in word {
+                    *bits &= !(1 << c);
                 }
-                if self.is_empty() {
-                    break;
+            }
+            Members::Wide {
+                ref mut chars,
+                ref mut count,
+            } => {
+                for &c in word {
+                    if *count == 0 {
+                        break;
+                    }
+                    let b = unsafe { chars.get_unchecked_mut(c) };
+                    if *b {
+                        *b = false;
+                        *count -= 1;
+                    }
                 }
             }
         }
     }
-    unsafe fn rm(&mut self, i: usize) {
-        let b = self.chars.get_unchecked_mut(i);
-        if *b {
-            *b = false;
-            self.count -= 1;
-        }
-    }
     pub fn is_empty(&self) -> bool {
-        self.count == 0
+        match self.members {
+            Members::Packed(bits) => bits == 0,
+            Members::Wide { count, .. } => count == 0,
+        }
     }
 }
 
@@ -67,34 +102,73 @@ impl CharSet {
 /// anagram calculation, a `CharCount` keeps track of the characters still
 /// looking for a foster word. To accelerate processing, they also cache
 /// the first character offset with a non-zero count, the last such offset,
-/// the sum of their counts, and a checksum sufficient for hashing and
-/// identification.
+/// the sum of their counts, and a hash maintained as the counts change.
+///
+/// A `CharCount` may also hold blanks, which stand for any one character.
+/// Words take what characters they can from those counted and use blanks
+/// only for the rest, so a word extracted from a `CharCount` always leaves
+/// the same residual.
+///
+/// The counts of an alphabet of at most `PACKED_ALPHABET` characters are
+/// kept inline as bytes, so a `CharCount` is copied without allocation,
+/// along with a bitmask of the characters present, from which the first and
+/// last offsets are found without a search. A count too large for a byte
+/// moves the counts into a vector.
 #[derive(Clone, Debug)]
 pub struct CharCount {
-    pub counts: Vec<usize>, // TODO pub only for debugging purposes
-    pub sum: usize,
+    counts: Counts,
+    pub sum: usize, // including blanks
+    pub blanks: usize,
     pub first: usize, // lowest index with any characters
     pub last: usize,  // highest index (+1) with any characters
-    hash: u128,       // for quick hashing and equality
+    hash: u64,        // for quick hashing and inequality
+}
+
+#[derive(Clone, Debug)]
+enum Counts {
+    // the counts of the first size characters, the rest being zero, and a
+    // bit per character with a positive count
+    Packed {
+        counts: [u8; PACKED_ALPHABET],
+        present: u64,
+        size: usize,
+    },
+    Wide(Vec<usize>),
 }
 
+/// The character standing for a blank in a phrase.
+pub const BLANK: char = '?';
+
+// the offset whose weight blanks contribute to the hash
+const BLANK_OFFSET: usize = usize::MAX;
+
 impl PartialEq for CharCount {
+    // the hash only speeds up the comparison of unequal counts; equality is
+    // always checked character by character, so there are no collisions
     fn eq(&self, other: &CharCount) -> bool {
-        if self.hashed() && other.hashed() {
-            self.hash == other.hash
-        } else {
-            if !(self.sum == other.sum && self.first == other.first && self.last == other.last) {
-                return false;
-            }
-            unsafe {
-                for i in 0..self.last {
-                    if *self.counts.get_unchecked(i) != *other.counts.get_unchecked(i) {
-                        return false;
-                    }
+        self.hash == other.hash
+            && self.sum == other.sum
+            && self.blanks == other.blanks
+            && self.first == other.first
+            && self.last == other.last
+            && match (&self.counts, &other.counts) {
+                (
+                    Counts::Packed {
+                        counts: a,
+                        present: p,
+                        ..
+                    },
+                    Counts::Packed {
+                        counts: b,
+                        present: q,
+                        ..
+                    },
+                ) => p == q && a == b,
+                (Counts::Wide(a), Counts::Wide(b)) => {
+                    a[self.first..self.last] == b[other.first..other.last]
                 }
+                _ => (self.first..self.last).all(|i| self.count(i) == other.count(i)),
             }
-            true
-        }
     }
 }
 
@@ -102,66 +176,182 @@ impl Eq for CharCount {}
 
 impl Hash for CharCount {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.hash.hash(state);
+        state.write_u64(self.hash);
+    }
+}
+
+/// A `Hasher` for `CharCount`s, which hash themselves, so it need do no more
+/// than scramble the bits it is given.
+#[derive(Default)]
+pub struct CharCountHasher(u64);
+
+impl Hasher for CharCountHasher {
+    fn finish(&self) -> u64 {
+        mix(self.0)
+    }
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = mix(self.0 ^ u64::from(b));
+        }
     }
+    fn write_u64(&mut self, n: u64) {
+        self.0 ^= n;
+    }
+}
+
+/// A map keyed by `CharCount`s.
+pub type CharCountMap<V> = HashMap<Arc<CharCount>, V, BuildHasherDefault<CharCountHasher>>;
+
+// the finalizer of SplitMix64, a cheap and thorough scrambling of bits
+fn mix(n: u64) -> u64 {
+    let mut z = n.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+// The hash of a `CharCount` is the sum of a pseudo-random weight per
+// character times its count, so it can be updated as characters come and go.
+#[inline]
+fn weight(i: usize) -> u64 {
+    mix(i as u64)
 }
 
 impl CharCount {
-    pub fn hashed(&self) -> bool {
-        self.hash > 0 || self.sum == 0
-    }
-    // mostly just for debugging
-    // fn confirm_mutable(&self) {
-    //     if self.hashed() {
-    //         panic!("count should be mutable")
-    //     }
-    // }
-    // calculate the hash -- this treats the character counts as a sort of
-    // odometer and reads of the values as one big base-10 number
-    pub fn calculate_hash(&mut self, powers_of_ten: &[u128]) {
-        if self.hashed() {
-            return; // already calculated
-        }
-        let mut accumulator: u128 = 0;
-        unsafe {
-            for i in self.first..self.last {
-                let c = self.counts.get_unchecked(i);
-                if c > &0 {
-                    let p = powers_of_ten.get_unchecked(i);
-                    let value = (c % 10) as u128;
-                    accumulator = accumulator + value * p;
-                }
+    /// An empty count of the characters of an alphabet of the given size.
+    pub fn new(alphabet_size: usize) -> CharCount {
+        let counts = if alphabet_size <= PACKED_ALPHABET {
+            Counts::Packed {
+                counts: [0; PACKED_ALPHABET],
+                present: 0,
+                size: alphabet_size,
             }
+        } else {
+            Counts::Wide(vec![0; alphabet_size])
+        };
+        CharCount {
+            counts,
+            sum: 0,
+            blanks: 0,
+            first: 0,
+            last: 1,
+            hash: 0,
+        }
+    }
+    /// The number of characters in the alphabet counted.
+    pub fn alphabet_size(&self) -> usize {
+        match self.counts {
+            Counts::Packed { size, .. } => size,
+            Counts::Wide(ref counts) => counts.len(),
         }
-        self.hash = accumulator;
     }
+    /// The count of the character at offset `i`.
+    pub fn count(&self, i: usize) -> usize {
+        match self.counts {
+            Counts::Packed { ref counts, .. } => counts[i] as usize,
+            Counts::Wide(ref counts) => counts[i],
+        }
+    }
+    /// The bytes the counts occupy beyond the `CharCount` itself.
+    pub fn allocated(&self) -> usize {
+        match self.counts {
+            Counts::Packed { .. } => 0,
+            Counts::Wide(ref counts) => counts.len() * ::std::mem::size_of::<usize>(),
+        }
+    }
+    #[inline]
+    unsafe fn get(&self, i: usize) -> usize {
+        match self.counts {
+            Counts::Packed { ref counts, .. } => *counts.get_unchecked(i) as usize,
+            Counts::Wide(ref counts) => *counts.get_unchecked(i),
+        }
+    }
+    // adds one to the count of the character at offset `i`, which must be
+    // in the alphabet
+    #[inline]
+    unsafe fn bump(&mut self, i: usize) {
+        let wide = match self.counts {
+            Counts::Packed {
+                ref mut counts,
+                ref mut present,
+                ..
+            } => {
+                let n = counts.get_unchecked_mut(i);
+                if *n < u8::MAX {
+                    *n += 1;
+                    *present |= 1 << i;
+                    return;
+                }
+                counts.iter().map(|&n| n as usize).collect::<Vec<_>>()
+            }
+            Counts::Wide(ref mut counts) => {
+                *counts.get_unchecked_mut(i) += 1;
+                return;
+            }
+        };
+        // the count will no longer fit in a byte
+        let size = self.alphabet_size();
+        let mut wide = wide;
+        wide.truncate(size);
+        *wide.get_unchecked_mut(i) += 1;
+        self.counts = Counts::Wide(wide);
+    }
+    /// Removes one instance of the character at offset `i`.
+    ///
+    /// # Safety
+    ///
+    /// `i` must be an offset whose count is positive.
     pub unsafe fn decrement(&mut self, i: usize) {
-        *self.counts.get_unchecked_mut(i) -= 1;
+        self.hash = self.hash.wrapping_sub(weight(i));
         self.sum -= 1;
-        if self.sum == 0 {
-            self.first = 0;
-            self.last = 1;
-        } else if self.first + 1 != self.last {
-            if self.sum == 1 {
-                for j in self.first..self.last {
-                    if *self.counts.get_unchecked(j) > 0 {
-                        self.first = j;
-                        self.last = j + 1;
-                        break;
-                    }
+        let letters = self.sum - self.blanks;
+        match self.counts {
+            Counts::Packed {
+                ref mut counts,
+                ref mut present,
+                ..
+            } => {
+                let n = counts.get_unchecked_mut(i);
+                *n -= 1;
+                if *n == 0 {
+                    *present &= !(1 << i);
                 }
-            } else if i == self.first {
-                for j in self.first..self.last {
-                    if *self.counts.get_unchecked(j) > 0 {
-                        self.first = j;
-                        break;
-                    }
+                if letters == 0 {
+                    self.first = 0;
+                    self.last = 1;
+                } else {
+                    self.first = present.trailing_zeros() as usize;
+                    self.last = PACKED_ALPHABET - present.leading_zeros() as usize;
                 }
-            } else if i + 1 == self.last {
-                for j in (self.first..self.last).rev() {
-                    if *self.counts.get_unchecked(j) > 0 {
-                        self.last = j + 1;
-                        break;
+            }
+            Counts::Wide(ref mut counts) => {
+                *counts.get_unchecked_mut(i) -= 1;
+                if letters == 0 {
+                    self.first = 0;
+                    self.last = 1;
+                } else if self.first + 1 != self.last {
+                    if letters == 1 {
+                        for j in self.first..self.last {
+                            if *counts.get_unchecked(j) > 0 {
+                                self.first = j;
+                                self.last = j + 1;
+                                break;
+                            }
+                        }
+                    } else if i == self.first {
+                        for j in self.first..self.last {
+                            if *counts.get_unchecked(j) > 0 {
+                                self.first = j;
+                                break;
+                            }
+                        }
+                    } else if i + 1 == self.last {
+                        for j in (self.first..self.last).rev() {
+                            if *counts.get_unchecked(j) > 0 {
+                                self.last = j + 1;
+                                break;
+                            }
+                        }
                     }
                 }
             }
@@ -169,34 +359,70 @@ impl CharCount {
     }
     pub fn add(&mut self, word: Vec<usize>) -> bool {
         for i in word {
-            if i > self.counts.len() {
+            if i >= self.alphabet_size() {
                 return false;
             }
             unsafe {
-                *self.counts.get_unchecked_mut(i) += 1;
+                self.bump(i);
             }
+            self.hash = self.hash.wrapping_add(weight(i));
             self.sum += 1;
         }
         true
     }
+    /// Adds this many blanks.
+    pub fn add_blanks(&mut self, n: usize) {
+        self.blanks += n;
+        self.sum += n;
+        self.hash = self
+            .hash
+            .wrapping_add(weight(BLANK_OFFSET).wrapping_mul(n as u64));
+    }
+    /// Removes one instance of the character at offset `i`, or failing that
+    /// a blank. This is whether a blank was used, or None if neither was
+    /// available.
+    pub fn take(&mut self, i: usize) -> Option<bool> {
+        if i < self.alphabet_size() && self.has(i) {
+            unsafe {
+                self.decrement(i);
+            }
+            Some(false)
+        } else if self.blanks > 0 {
+            self.blanks -= 1;
+            self.sum -= 1;
+            self.hash = self.hash.wrapping_sub(weight(BLANK_OFFSET));
+            Some(true)
+        } else {
+            None
+        }
+    }
+    /// Removes the characters of the word, using blanks for those it has run
+    /// out of. If it runs out of blanks too, this is the offset in the word
+    /// of the character unavailable, along with a copy of the word.
     pub fn subtract(&mut self, word: Vec<usize>) -> Option<(usize, Vec<usize>)> {
-        // self.confirm_mutable();
-        let copy = word.clone();
         for (idx, &i) in word.iter().enumerate() {
-            if i >= self.counts.len() || self.counts[i] == 0 {
-                return Some((idx, copy));
+            if self.take(i).is_none() {
+                return Some((idx, word));
             }
-            self.counts[i] -= 1;
-            self.sum -= 1;
         }
         None
     }
     pub fn set_limits(&mut self) {
+        if let Counts::Packed { present, .. } = self.counts {
+            if present == 0 {
+                self.first = 0;
+                self.last = 1;
+            } else {
+                self.first = present.trailing_zeros() as usize;
+                self.last = PACKED_ALPHABET - present.leading_zeros() as usize;
+            }
+            return;
+        }
         let mut looking_for_first = true;
         let mut first = 0;
         let mut last = 0;
-        for (i, &c) in self.counts.iter().enumerate() {
-            if c > 0 {
+        for i in 0..self.alphabet_size() {
+            if self.count(i) > 0 {
                 last = i;
                 if looking_for_first {
                     first = i;
@@ -208,7 +434,8 @@ impl CharCount {
         self.last = last + 1;
     }
     unsafe fn increment(&mut self, i: usize) {
-        *self.counts.get_unchecked_mut(i) += 1;
+        self.bump(i);
+        self.hash = self.hash.wrapping_add(weight(i));
         if self.sum == 0 {
             self.first = i;
             self.last = i + 1;
@@ -220,18 +447,33 @@ impl CharCount {
         self.sum += 1;
     }
     pub fn has(&self, i: usize) -> bool {
-        unsafe {
-            let v = *self.counts.get_unchecked(i);
-            // println!("{} => {}; {:?}", i, v, self);
-            v > 0
+        match self.counts {
+            Counts::Packed { present, .. } => present >> i & 1 == 1,
+            Counts::Wide(ref counts) => unsafe { *counts.get_unchecked(i) > 0 },
         }
     }
     pub fn to_set(&self) -> CharSet {
-        CharSet::new(&self.counts)
+        match self.counts {
+            Counts::Packed { present, .. } => CharSet {
+                members: Members::Packed(present),
+            },
+            Counts::Wide(ref counts) => CharSet::new(counts),
+        }
     }
     pub fn is_empty(&self) -> bool {
         self.sum == 0
     }
+    /// The characters counted, each repeated as often as it occurs, in
+    /// offset order. Blanks are not included.
+    // repeat_n is too new for the compilers rana still builds with
+    #[allow(unknown_lints, clippy::manual_repeat_n)]
+    pub fn letters(&self) -> Vec<usize> {
+        let mut letters = Vec::with_capacity(self.sum);
+        for i in 0..self.alphabet_size() {
+            letters.extend(::std::iter::repeat(i).take(unsafe { self.get(i) }));
+        }
+        letters
+    }
 }
 /// A `Translator` converts between alphabetic and numeric representations of
 /// words. For anagram calculation words are treated as pure numeric sequences.
@@ -248,6 +490,14 @@ impl Translator {
     pub fn new<'a, I: Iterator<Item = &'a str>>(
         normalizer: fn(&str) -> String,
         i: I,
+    ) -> Translator {
+        Translator::ordered(normalizer, i, &LetterOrder::Frequency)
+    }
+    /// A `Translator` numbering the letters of the words in the given order.
+    pub fn ordered<'a, I: Iterator<Item = &'a str>>(
+        normalizer: fn(&str) -> String,
+        i: I,
+        order: &LetterOrder,
     ) -> Translator {
         let mut count_map = HashMap::new();
         for word in i {
@@ -255,13 +505,13 @@ impl Translator {
                 *count_map.entry(c).or_insert(0) += 1;
             }
         }
-        let mut counts = count_map.into_iter().collect::<Vec<_>>();
-        counts.sort_by(|&(_, ref a), &(_, ref b)| b.cmp(a));
-        let map: HashMap<char, usize> = counts
-            .into_iter()
-            .enumerate()
-            .map(|(i, (c, _))| (c, i))
-            .collect();
+        let alphabet = order.arrange(count_map.into_iter().collect());
+        Translator::from_alphabet(normalizer, &alphabet)
+    }
+    /// Reconstitutes a `Translator` from the characters of its alphabet in
+    /// numeric order.
+    pub fn from_alphabet(normalizer: fn(&str) -> String, alphabet: &[char]) -> Translator {
+        let map: HashMap<char, usize> = alphabet.iter().enumerate().map(|(i, &c)| (c, i)).collect();
         let map_back = map.iter().map(|(&c, &i)| (i, c)).collect();
         Translator {
             normalizer,
@@ -269,17 +519,15 @@ impl Translator {
             map_back,
         }
     }
+    /// The characters of the alphabet in numeric order.
+    pub fn alphabet(&self) -> Vec<char> {
+        (0..self.map.len()).map(|i| self.map_back[&i]).collect()
+    }
     pub fn alphabet_size(&self) -> usize {
         self.map.len()
     }
     pub fn count(&self, word: &str) -> Option<CharCount> {
-        let mut cc = CharCount {
-            counts: vec![0; self.map.len()],
-            sum: 0,
-            first: 0,
-            last: 1,
-            hash: 0,
-        };
+        let mut cc = CharCount::new(self.map.len());
         for c in word.chars() {
             if let Some(&i) = self.map.get(&c) {
                 unsafe {
@@ -313,7 +561,7 @@ impl Translator {
                 None => return None,
             }
         }
-        return Some(translation);
+        Some(translation)
     }
     /// for construction of an error message when translate fails
     pub fn unfamiliar_character(&self, word: &str) -> (String, String) {
@@ -352,43 +600,121 @@ pub fn normalize(word: &str) -> String {
 /// The representation of a partially processed phrase working its way through
 /// anagram discovery. `ToDo`s are a linked list keeping track of words already
 /// found plus a `CharCount` keeping track of the characters yet to be
-/// processed.
+/// processed. A `ToDo` holds its word and residual by its offset into the
+/// `WordList` they were found in, which it shares with its siblings, so
+/// making one costs no allocation and no more reference counting than that
+/// of the list and its parent.
+///
+/// A search may also keep track of which `ToDo`s come to nothing: a `ToDo`
+/// does once it has no children left which might still become anagrams and
+/// none has. This bookkeeping shares a `u32` with the offset's padding, so
+/// it makes a `ToDo` no bigger.
 #[derive(Debug)]
 pub struct ToDo {
     parent: Option<Arc<ToDo>>,
-    pub word: Arc<Vec<usize>>,
-    pub undone: Arc<CharCount>,
+    list: WordList,
+    offset: u32,
+    /// the number of words found
+    pub depth: usize,
+    // the children still being worked on, with the COMPLETED bit set if
+    // some descendant is an anagram
+    state: AtomicU32,
 }
 
+// the bit of a ToDo's state set once some descendant is an anagram
+const COMPLETED: u32 = 1 << 31;
+
 impl ToDo {
-    pub fn new(parent: Arc<ToDo>, word: Arc<Vec<usize>>, undone: Arc<CharCount>) -> ToDo {
+    /// The `ToDo` which adds to its parent the word at the offset into the
+    /// list.
+    pub fn new(parent: Arc<ToDo>, list: WordList, offset: u32) -> ToDo {
         ToDo {
+            depth: parent.depth + 1,
             parent: Some(parent),
-            word,
-            undone,
+            list,
+            offset,
+            state: AtomicU32::new(0),
         }
     }
     pub fn seed(undone: CharCount) -> ToDo {
         ToDo {
             parent: None,
-            word: Arc::new(Vec::with_capacity(0)),
-            undone: Arc::new(undone),
+            list: Arc::new(vec![(Arc::new(Vec::with_capacity(0)), Arc::new(undone))]),
+            offset: 0,
+            depth: 0,
+            state: AtomicU32::new(0),
         }
     }
-    fn trace(&self, words: &mut Vec<Vec<usize>>) {
-        if !self.word.is_empty() {
-            words.push((*self.word).clone());
-            if let Some(ref t) = self.parent {
-                t.trace(words);
-            }
-        }
+    /// The last word found.
+    pub fn word(&self) -> &Arc<Vec<usize>> {
+        &self.list[self.offset as usize].0
+    }
+    /// The characters yet to be processed.
+    pub fn undone(&self) -> &Arc<CharCount> {
+        &self.list[self.offset as usize].1
+    }
+    /// The words found, from the last back to the first, without copying
+    /// them.
+    pub fn found(&self) -> Found<'_> {
+        Found { todo: Some(self) }
     }
     pub fn words(&self) -> Vec<Vec<usize>> {
-        let mut words = vec![];
-        self.trace(&mut words);
-        words
+        self.found().map(|word| word.to_vec()).collect()
     }
     pub fn done(&self) -> bool {
-        self.undone.is_empty()
+        self.undone().is_empty()
+    }
+    /// Notes how many children of this `ToDo` are to be worked on. This must
+    /// be done before any of them is.
+    pub fn expect(&self, children: usize) {
+        // the state may already be marked completed; children are counted
+        // by their offsets into a word list, so they fit under that bit
+        self.state.fetch_add(children as u32, Ordering::SeqCst);
+    }
+    /// Notes that this `ToDo` has a child which is an anagram, as do its
+    /// ancestors.
+    pub fn complete(&self) {
+        let mut todo = Some(self);
+        while let Some(t) = todo {
+            if t.state.fetch_or(COMPLETED, Ordering::SeqCst) & COMPLETED != 0 {
+                // the ancestors already know
+                break;
+            }
+            todo = t.parent.as_deref();
+        }
+    }
+    /// Notes that this `ToDo` has come to nothing, passing it to `dead`
+    /// along with each ancestor which thereby comes to nothing too.
+    pub fn abandon<F: FnMut(&ToDo)>(&self, mut dead: F) {
+        dead(self);
+        let mut parent = self.parent.as_deref();
+        while let Some(p) = parent {
+            // a parent whose other children are still pending, or which
+            // has an anagram among its descendants, has not come to nothing
+            if p.state.fetch_sub(1, Ordering::SeqCst) != 1 {
+                break;
+            }
+            dead(p);
+            parent = p.parent.as_deref();
+        }
+    }
+}
+
+/// An iterator over the words in a chain of `ToDo`s, from the last found
+/// back to the first.
+pub struct Found<'a> {
+    todo: Option<&'a ToDo>,
+}
+
+impl<'a> Iterator for Found<'a> {
+    type Item = &'a [usize];
+    fn next(&mut self) -> Option<&'a [usize]> {
+        let todo = self.todo?;
+        if todo.word().is_empty() {
+            self.todo = None;
+            return None;
+        }
+        self.todo = todo.parent.as_deref();
+        Some(todo.word())
     }
 }
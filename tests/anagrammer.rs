@@ -0,0 +1,781 @@
+extern crate ranagrams;
+
+use ranagrams::anagrammer::{Anagram, Anagrammer, AnagrammerBuilder};
+use ranagrams::dictionary;
+use ranagrams::letter_order::LetterOrder;
+use ranagrams::order::Order;
+use ranagrams::score::Combiner;
+use ranagrams::strategy::Strategy;
+
+const WORDS: &[&str] = &["a", "at", "ate", "cat", "eat", "et", "eta", "tea", "tee"];
+
+fn builder(phrase: &str) -> AnagrammerBuilder {
+    AnagrammerBuilder::new()
+        .dictionary_words(WORDS)
+        .phrase(phrase)
+        .threads(2)
+}
+
+// anagrams with their words in alphabetical order, sorted
+fn anagrams(anagrammer: &Anagrammer) -> Vec<String> {
+    let mut found: Vec<String> = anagrammer
+        .anagrams()
+        .map(|anagram| {
+            let mut words = anagram.words;
+            words.sort();
+            words.join(" ")
+        })
+        .collect();
+    found.sort();
+    found
+}
+
+#[test]
+fn anagrams_of_eat() {
+    let anagrammer = builder("eat").build().unwrap();
+    assert_eq!(
+        vec!["a et", "ate", "eat", "eta", "tea"],
+        anagrams(&anagrammer)
+    );
+}
+
+#[test]
+fn include_and_exclude() {
+    let anagrammer = builder("tea cat")
+        .include("cat")
+        .exclude("eta")
+        .build()
+        .unwrap();
+    assert_eq!(
+        vec!["a cat et", "ate cat", "cat eat", "cat tea"],
+        anagrams(&anagrammer)
+    );
+}
+
+#[test]
+fn shapes() {
+    let all = anagrams(&builder("tea cat").build().unwrap());
+    let with = |f: &dyn Fn(&str) -> bool| -> Vec<String> {
+        all.iter().filter(|a| f(a)).cloned().collect()
+    };
+    let count = |a: &str| a.split(' ').count();
+    let longest = |a: &str| a.split(' ').map(str::len).max().unwrap();
+    let shaped = |b: AnagrammerBuilder| anagrams(&b.build().unwrap());
+    assert_eq!(
+        with(&|a| count(a) == 2),
+        shaped(builder("tea cat").minimum_words(2).maximum_words(2))
+    );
+    assert_eq!(
+        with(&|a| count(a) >= 3),
+        shaped(builder("tea cat").minimum_words(3))
+    );
+    assert_eq!(
+        with(&|a| count(a) <= 3 && longest(a) <= 2),
+        shaped(builder("tea cat").maximum_words(3).maximum_word_length(2))
+    );
+    // included words count
+    assert_eq!(
+        vec!["a cat et"],
+        shaped(builder("tea cat").include("cat").minimum_words(3))
+    );
+    assert!(shaped(
+        builder("tea cat")
+            .include("cat")
+            .include("tea")
+            .minimum_words(3)
+    )
+    .is_empty());
+    assert!(shaped(
+        builder("tea cat")
+            .include("cat")
+            .include("a")
+            .maximum_words(1)
+    )
+    .is_empty());
+}
+
+#[test]
+fn patterns() {
+    let found = |pattern: &str| -> Vec<String> {
+        let anagrammer = builder("tea cat").pattern(pattern).build().unwrap();
+        let mut found: Vec<String> = anagrammer.anagrams().map(|a| a.to_string()).collect();
+        found.sort();
+        found
+    };
+    assert_eq!(vec!["a et cat"], found("1 2 3"));
+    // every order fitting the template is found
+    assert_eq!(vec!["a cat et", "a et cat"], found("(1, 2-, 2-)"));
+    assert_eq!(
+        vec![
+            "ate cat", "cat ate", "cat eat", "cat eta", "cat tea", "eat cat", "eta cat", "tea cat"
+        ],
+        found("3 _")
+    );
+    assert!(found("3 4").is_empty());
+    let error = |b: AnagrammerBuilder| b.build().err().unwrap().exit_code();
+    assert_eq!(2, error(builder("tea").pattern("3 x")));
+    assert_eq!(2, error(builder("tea cat").pattern("3 _").include("cat")));
+}
+
+#[test]
+fn matches() {
+    let all = anagrams(&builder("tea cat").build().unwrap());
+    let with = |f: &dyn Fn(&[&str]) -> bool| -> Vec<String> {
+        all.iter()
+            .filter(|a| f(&a.split(' ').collect::<Vec<_>>()))
+            .cloned()
+            .collect()
+    };
+    let found = |b: AnagrammerBuilder| anagrams(&b.build().unwrap());
+    assert_eq!(
+        with(&|words| words.iter().any(|w| w.starts_with('e'))),
+        found(builder("tea cat").require_match("^e", 1))
+    );
+    assert_eq!(
+        with(&|words| words.iter().filter(|w| w.starts_with('a')).count() >= 2),
+        found(builder("tea cat").require_match("^a", 2))
+    );
+    assert_eq!(
+        with(&|words| !words.iter().any(|w| w.ends_with('a'))),
+        found(builder("tea cat").forbid_match("a$"))
+    );
+    // included words count
+    assert_eq!(
+        vec!["a cat et", "ate cat", "cat eat", "cat eta", "cat tea"],
+        found(builder("tea cat").include("cat").require_match("^c", 1))
+    );
+    assert!(found(builder("tea cat").require_match("^c", 2)).is_empty());
+    let error = |b: AnagrammerBuilder| b.build().err().unwrap().exit_code();
+    assert_eq!(2, error(builder("tea").require_match("(", 1)));
+    assert_eq!(2, error(builder("tea").require_match("t", 0)));
+    assert_eq!(2, error(builder("tea").forbid_match("[")));
+}
+
+#[test]
+fn leftovers() {
+    let found = |b: AnagrammerBuilder| -> Vec<String> {
+        b.build()
+            .unwrap()
+            .anagrams()
+            .map(|a| a.to_string())
+            .collect()
+    };
+    let all = found(builder("teat").leftover(1));
+    // anagrams using every letter come first
+    assert_eq!("et at", all[0]);
+    let mut rest = all[1..].to_vec();
+    rest.sort();
+    assert_eq!(
+        vec!["ate [+t]", "eat [+t]", "et a [+t]", "eta [+t]", "tea [+t]"],
+        rest
+    );
+    // 75% of four letters is three
+    assert_eq!(all.len(), found(builder("teat").use_at_least(75)).len());
+    assert_eq!(
+        1,
+        found(builder("teat").leftover(1).use_at_least(100)).len()
+    );
+    let anagrammer = builder("teat").leftover(2).build().unwrap();
+    let leftovers: Vec<usize> = anagrammer.anagrams().map(|a| a.leftover.len()).collect();
+    assert!(leftovers.windows(2).all(|w| w[0] <= w[1]));
+    assert!(leftovers.contains(&2));
+    let error = |b: AnagrammerBuilder| b.build().err().unwrap().exit_code();
+    assert_eq!(2, error(builder("tea").use_at_least(101)));
+}
+
+#[test]
+fn blanks() {
+    let found = |b: AnagrammerBuilder| -> Vec<String> {
+        let mut found: Vec<String> = b
+            .build()
+            .unwrap()
+            .anagrams()
+            .map(|a| a.to_string())
+            .collect();
+        found.sort();
+        found
+    };
+    // the letters blanks stand for are in uppercase
+    assert_eq!(
+        vec!["Ate", "eAt", "et A", "etA", "teA", "teE"],
+        found(builder("te?"))
+    );
+    // each anagram is found once however the blanks might be spent
+    assert_eq!(
+        vec!["cAT ate", "cAT eat", "cAT et a", "cAT eta", "cAT tea", "caT teE"],
+        found(
+            builder("tea c??")
+                .minimum_words(2)
+                .maximum_words(3)
+                .require_match("^c", 1)
+        )
+    );
+    assert_eq!(
+        vec!["tEA"],
+        found(builder("t??").include("tea").maximum_words(1))
+    );
+    // unused blanks are left over as such
+    assert_eq!(
+        vec!["At [+e]", "Ate", "eAt", "et [+?]", "etA", "teA", "teE"],
+        found(builder("te?").minimum_word_length(2).leftover(1))
+    );
+    // residuals with blanks survive a memo file
+    let memo = std::env::temp_dir().join(format!("rana-blanks-{}.memo", std::process::id()));
+    let memo = memo.to_str().unwrap().to_string();
+    std::fs::remove_file(&memo).ok();
+    let mut first = builder("tea c??").memo_file(&memo).build().unwrap();
+    let expected = anagrams(&first);
+    let saved = first.save_memo().unwrap();
+    let second = builder("tea c??").memo_file(&memo).build().unwrap();
+    assert_eq!(saved, second.restored());
+    assert_eq!(expected, anagrams(&second));
+    std::fs::remove_file(&memo).ok();
+}
+
+#[test]
+fn words_in() {
+    let anagrammer = builder("tea").minimum_word_length(2).build().unwrap();
+    assert_eq!(
+        vec!["at", "ate", "eat", "et", "eta", "tea"],
+        anagrammer.words_in()
+    );
+    // the search learns no word uses "c", but that must not hide the words
+    // which use the rest
+    let anagrammer = builder("tee c").build().unwrap();
+    assert!(anagrams(&anagrammer).is_empty());
+    assert_eq!(vec!["et", "tee"], anagrammer.words_in());
+    let anagrammer = builder("teat").build().unwrap();
+    // "a" occurs only with "tet", which isn't a word
+    assert_eq!(vec!["at", "et"], anagrammer.strict_words_in());
+}
+
+#[test]
+fn errors() {
+    let error = |b: AnagrammerBuilder| b.build().err().unwrap().exit_code();
+    assert_eq!(2, error(builder("tea").threads(0)));
+    assert_eq!(3, error(builder("tea").dictionary_file("/no/such/file")));
+    assert_eq!(4, error(builder("tax")));
+    assert_eq!(5, error(builder("tea").include("tee")));
+    assert_eq!(2, error(builder("tea").maximum_words(0)));
+    assert_eq!(2, error(builder("tea").minimum_words(3).maximum_words(2)));
+    assert_eq!(
+        2,
+        error(builder("tea").minimum_word_length(3).maximum_word_length(2))
+    );
+}
+
+#[test]
+fn repeated_letters() {
+    // the anagrams of n a's are the partitions of n into parts of at most 3
+    for &(n, partitions) in &[(10, 14), (20, 44), (30, 91)] {
+        for &cache in &[true, false] {
+            let anagrammer = AnagrammerBuilder::new()
+                .dictionary_words(["a", "aa", "aaa"])
+                .phrase(&"a".repeat(n))
+                .cache(cache)
+                .build()
+                .unwrap();
+            assert_eq!(partitions, anagrammer.anagrams().count());
+        }
+    }
+}
+
+#[test]
+fn large_alphabet() {
+    let letters: Vec<String> = "abcdefghijklmnopqrstuvwxyzαβγδεζηθικλμνξο"
+        .chars()
+        .map(|c| c.to_string())
+        .collect();
+    let anagrammer = AnagrammerBuilder::new()
+        .dictionary_words(letters)
+        .phrase("aο")
+        .build()
+        .unwrap();
+    assert_eq!(vec!["a ο"], anagrams(&anagrammer));
+}
+
+#[test]
+fn iterator() {
+    let anagrammer = builder("tea").include("a").build().unwrap();
+    let anagram = anagrammer.anagrams().next().unwrap();
+    assert_eq!(vec!["a", "et"], anagram.words);
+    assert_eq!(2, anagram.numbers.len());
+    assert_eq!("a et", anagram.to_string());
+    // dropping a partially consumed iterator stops its workers
+    let anagrammer = builder("eat").build().unwrap();
+    assert_eq!(2, anagrammer.anagrams().take(2).count());
+}
+
+#[test]
+fn compiled_dictionary() {
+    let text = std::env::temp_dir().join(format!("rana-test-{}.txt", std::process::id()));
+    let text = text.to_str().unwrap().to_string();
+    std::fs::write(&text, WORDS.join("\n")).unwrap();
+    let (translator, root) = dictionary::read_text(&text).unwrap();
+    let compiled = dictionary::compiled_path(&text);
+    dictionary::write(&compiled, &translator, &root).unwrap();
+    for path in &[&text, &compiled] {
+        let anagrammer = AnagrammerBuilder::new()
+            .dictionary_file(path)
+            .phrase("eat")
+            .build()
+            .unwrap();
+        assert_eq!(
+            vec!["a et", "ate", "eat", "eta", "tea"],
+            anagrams(&anagrammer)
+        );
+    }
+    // a damaged compiled dictionary is an unreadable dictionary
+    let mut bytes = std::fs::read(&compiled).unwrap();
+    let version = bytes[4..8].to_vec();
+    let last = bytes.len() - 1;
+    bytes[last] ^= 1;
+    std::fs::write(&compiled, bytes).unwrap();
+    let error = AnagrammerBuilder::new()
+        .dictionary_file(&compiled)
+        .phrase("eat")
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(3, error.exit_code());
+    // as is one claiming more letters, or more nodes, than it could hold
+    let letters = u32::MAX.to_le_bytes().to_vec();
+    let nodes = [0u32.to_le_bytes(), u32::MAX.to_le_bytes()].concat();
+    for body in &[letters, nodes] {
+        let mut bytes = b"rana".to_vec();
+        bytes.extend_from_slice(&version);
+        bytes.extend_from_slice(&dictionary::checksum(body).to_le_bytes());
+        bytes.extend_from_slice(body);
+        std::fs::write(&compiled, bytes).unwrap();
+        let error = AnagrammerBuilder::new()
+            .dictionary_file(&compiled)
+            .phrase("eat")
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(3, error.exit_code());
+    }
+    std::fs::remove_file(&text).ok();
+    std::fs::remove_file(&compiled).ok();
+}
+
+#[test]
+fn cache_budget() {
+    let unlimited = builder("tea cat").build().unwrap();
+    let expected = anagrams(&unlimited);
+    assert_eq!(0, unlimited.cache_stats().evictions);
+    // a cache too small to hold anything still finds every anagram
+    let starved = builder("tea cat").cache_memory(1).build().unwrap();
+    assert_eq!(expected, anagrams(&starved));
+    let stats = starved.cache_stats();
+    assert_eq!(0, stats.entries);
+    assert!(stats.evictions > 0);
+}
+
+#[test]
+fn memo_file() {
+    let memo = std::env::temp_dir().join(format!("rana-test-{}.memo", std::process::id()));
+    let memo = memo.to_str().unwrap().to_string();
+    std::fs::remove_file(&memo).ok();
+    let mut first = builder("tea cat").memo_file(&memo).build().unwrap();
+    let expected = anagrams(&first);
+    assert_eq!(0, first.restored());
+    let saved = first.save_memo().unwrap();
+    assert!(saved > 0);
+    // nothing new is found the second time around
+    assert_eq!(0, first.save_memo().unwrap());
+    // a damaged tail is ignored
+    let mut bytes = std::fs::read(&memo).unwrap();
+    bytes.extend_from_slice(&[1, 2, 3]);
+    std::fs::write(&memo, bytes).unwrap();
+    let mut second = builder("tea cat").memo_file(&memo).build().unwrap();
+    assert_eq!(saved, second.restored());
+    assert_eq!(expected, anagrams(&second));
+    assert_eq!(0, second.save_memo().unwrap());
+    // only the entries fitting within a shorter phrase are restored
+    let third = builder("tea").memo_file(&memo).build().unwrap();
+    assert!(third.restored() > 0 && third.restored() < saved);
+    assert_eq!(vec!["a et", "ate", "eat", "eta", "tea"], anagrams(&third));
+    // the memo is only good for the configuration it was made with
+    let fourth = builder("tea cat")
+        .minimum_word_length(2)
+        .memo_file(&memo)
+        .build()
+        .unwrap();
+    assert_eq!(0, fourth.restored());
+    std::fs::remove_file(&memo).ok();
+}
+
+#[test]
+fn sort_by_score() {
+    let words = [
+        "a\t4", "at\t1", "ate\t2", "eat\t8", "et\t4", "eta", "tea\t0.5",
+    ];
+    let anagrammer = AnagrammerBuilder::new()
+        .dictionary_words(words)
+        .phrase("eat")
+        .exclude("ate")
+        .build()
+        .unwrap();
+    let sorted = |combiner| -> Vec<String> {
+        anagrammer
+            .anagrams_by_score(combiner)
+            .into_iter()
+            .map(|anagram| {
+                let mut words = anagram.words;
+                words.sort();
+                words.join(" ")
+            })
+            .collect()
+    };
+    assert_eq!(
+        vec!["eat", "a et", "eta", "tea"],
+        sorted(Combiner::GeometricMean)
+    );
+    // ties are broken by the words
+    assert_eq!(vec!["eat", "a et", "eta", "tea"], sorted(Combiner::Sum));
+    assert_eq!(vec!["eat", "a et", "eta", "tea"], sorted(Combiner::Minimum));
+    // included words are weighed too
+    let anagrammer = AnagrammerBuilder::new()
+        .dictionary_words(words)
+        .phrase("eat")
+        .include("tea")
+        .build()
+        .unwrap();
+    let anagram = anagrammer.anagrams().next().unwrap();
+    assert_eq!(vec![0.5], anagram.weights);
+    assert_eq!(0.5, anagram.score(Combiner::Sum));
+    // a bad weight is an invalid dictionary
+    let error = AnagrammerBuilder::new()
+        .dictionary_words(["a\tlots"])
+        .phrase("a")
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(2, error.exit_code());
+}
+
+#[test]
+fn top() {
+    let words = [
+        "a\t4",
+        "at\t1",
+        "ate\t2",
+        "cat\t3",
+        "eat\t8",
+        "et\t4",
+        "eta",
+        "tea\t0.5",
+        "tee\t6",
+        "act\t2",
+        "tact\t5",
+        "ace\t0.25",
+        "c\t0.125",
+        "t\t1.5",
+        "e\t0.75",
+    ];
+    for &combiner in &[Combiner::Sum, Combiner::Minimum, Combiner::GeometricMean] {
+        for &threads in &[1, 3] {
+            let anagrammer = AnagrammerBuilder::new()
+                .dictionary_words(words)
+                .phrase("tea cat et")
+                .include("a")
+                .threads(threads)
+                .build()
+                .unwrap();
+            let all: Vec<f64> = anagrammer
+                .anagrams_by_score(combiner)
+                .iter()
+                .map(|anagram| anagram.score(combiner))
+                .collect();
+            assert!(all.len() > 10);
+            for &k in &[0, 1, 5, all.len() + 1] {
+                let top: Vec<f64> = anagrammer
+                    .top(k, combiner)
+                    .map(|anagram| anagram.score(combiner))
+                    .collect();
+                assert_eq!(&all[..k.min(all.len())], &top[..]);
+            }
+        }
+    }
+}
+
+#[test]
+fn sample() {
+    let sampled = |b: AnagrammerBuilder, n: usize| -> Vec<String> {
+        let mut found: Vec<String> = b
+            .build()
+            .unwrap()
+            .sample(n)
+            .unwrap()
+            .into_iter()
+            .map(|anagram| {
+                let mut words = anagram.words;
+                words.sort();
+                words.join(" ")
+            })
+            .collect();
+        found.sort();
+        found
+    };
+    for phrase in &["eat", "tea cat", "te?"] {
+        let all = anagrams(&builder(phrase).build().unwrap());
+        // asking for more than there are gets them all
+        assert_eq!(all, sampled(builder(phrase), all.len() + 3));
+        let some = sampled(builder(phrase), 2);
+        assert_eq!(2, some.len());
+        assert!(some[0] < some[1]);
+        assert!(some.iter().all(|a| all.contains(a)));
+    }
+    assert_eq!(
+        anagrams(&builder("tea cat").include("cat").build().unwrap()),
+        sampled(builder("tea cat").include("cat"), 100)
+    );
+    assert!(sampled(builder("tat"), 5).is_empty());
+    let error = |b: AnagrammerBuilder| b.build().unwrap().sample(1).err().unwrap().exit_code();
+    assert_eq!(2, error(builder("tea").minimum_words(2)));
+    assert_eq!(2, error(builder("tea").leftover(1)));
+}
+
+#[test]
+fn count() {
+    for phrase in &["eat", "tea cat", "te?", "tat", "tea cat tee"] {
+        let anagrammer = builder(phrase).build().unwrap();
+        let expected = anagrammer.anagrams().count() as u64;
+        assert_eq!(Some(expected), anagrammer.count().unwrap().to_u64());
+    }
+    let anagrammer = builder("tea cat").include("cat").build().unwrap();
+    assert_eq!("5", anagrammer.count().unwrap().to_string());
+    // the anagrams of 500 a's made of runs of a's are the partitions of 500
+    let runs: Vec<String> = (1..=500).map(|n| "a".repeat(n)).collect();
+    let anagrammer = AnagrammerBuilder::new()
+        .dictionary_words(runs)
+        .phrase(&"a".repeat(500))
+        .build()
+        .unwrap();
+    let count = anagrammer.count().unwrap();
+    assert_eq!(None, count.to_u64());
+    assert_eq!("2300165032574323995027", count.to_string());
+    let error = |b: AnagrammerBuilder| b.build().unwrap().count().err().unwrap().exit_code();
+    assert_eq!(2, error(builder("tea").pattern("3")));
+}
+
+#[test]
+fn wide_alphabet() {
+    // more letters than a count can pack
+    let mut words: Vec<String> = ('a'..='z')
+        .chain('α'..='ω')
+        .chain('а'..='я')
+        .map(|c| c.to_string())
+        .collect();
+    assert!(words.len() > 64);
+    words.push("aя".to_string());
+    let anagrammer = AnagrammerBuilder::new()
+        .dictionary_words(words)
+        .phrase("яωa")
+        .build()
+        .unwrap();
+    assert_eq!(vec!["a ω я", "aя ω"], anagrams(&anagrammer));
+    assert_eq!(Some(2), anagrammer.count().unwrap().to_u64());
+}
+
+#[test]
+fn seed() {
+    let shuffled = |seed: u64, threads: usize| -> Vec<String> {
+        builder("tea cat eat")
+            .shuffle(true)
+            .seed(seed)
+            .threads(threads)
+            .build()
+            .unwrap()
+            .anagrams()
+            .map(|a| a.to_string())
+            .collect()
+    };
+    let first = shuffled(7, 1);
+    assert_eq!(first, shuffled(7, 1));
+    // the order does not depend on the thread count
+    assert_eq!(first, shuffled(7, 4));
+    let mut sorted = first.clone();
+    sorted.sort();
+    let mut expected = shuffled(8, 2);
+    expected.sort();
+    assert_eq!(expected, sorted);
+    let sampled = |seed: u64| -> Vec<String> {
+        builder("tea cat eat")
+            .seed(seed)
+            .build()
+            .unwrap()
+            .sample(5)
+            .unwrap()
+            .into_iter()
+            .map(|a| a.to_string())
+            .collect()
+    };
+    assert_eq!(sampled(3), sampled(3));
+}
+
+#[test]
+fn sorted() {
+    let listed = |b: AnagrammerBuilder, order: Order| -> Vec<String> {
+        b.build()
+            .unwrap()
+            .sorted(order)
+            .unwrap()
+            .map(|a| a.to_string())
+            .collect()
+    };
+    let expected = |b: AnagrammerBuilder, order: Order| -> Vec<String> {
+        let mut all: Vec<Anagram> = b
+            .build()
+            .unwrap()
+            .anagrams()
+            .map(|mut a| {
+                a.words.sort_by_key(|w| w.to_lowercase());
+                a
+            })
+            .collect();
+        all.sort_by(|a, b| order.compare(a, b));
+        all.into_iter().map(|a| a.to_string()).collect()
+    };
+    let orders = [Order::Lexicographic, Order::FewestWords, Order::LongestWord];
+    for &order in &orders {
+        for &threads in &[1, 3] {
+            let cases: Vec<Box<dyn Fn() -> AnagrammerBuilder>> = vec![
+                Box::new(move || builder("tea cat").threads(threads)),
+                Box::new(move || builder("tea cat eat").threads(threads)),
+                Box::new(move || builder("tea c??").threads(threads).maximum_words(3)),
+                Box::new(move || builder("teat").threads(threads).leftover(2)),
+                Box::new(move || builder("tea cat").threads(threads).require_match("^e", 1)),
+            ];
+            for case in &cases {
+                // which letters blanks stand for depends on the word order
+                let lower = |v: Vec<String>| -> Vec<String> {
+                    v.into_iter().map(|a| a.to_lowercase()).collect()
+                };
+                let sorted = lower(listed(case(), order));
+                assert!(!sorted.is_empty());
+                assert_eq!(lower(expected(case(), order)), sorted);
+            }
+        }
+    }
+    assert_eq!(
+        vec!["cat a et", "cat ate", "cat eat", "cat eta", "cat tea"],
+        listed(builder("tea cat").include("cat"), Order::Lexicographic)
+    );
+    assert_eq!(
+        vec!["eat"],
+        listed(builder("eat").include("eat"), Order::Lexicographic)
+    );
+    let fewest = listed(builder("tea cat"), Order::FewestWords);
+    assert_eq!("a cat et", fewest[fewest.len() - 1]);
+    let error = |b: AnagrammerBuilder| {
+        b.build()
+            .unwrap()
+            .sorted(Order::Lexicographic)
+            .err()
+            .unwrap()
+            .exit_code()
+    };
+    assert_eq!(2, error(builder("tea").pattern("3")));
+}
+
+#[test]
+fn pruning() {
+    for phrase in &["tea cat eat", "tee tat cat", "te? cat"] {
+        for &threads in &[1, 3] {
+            let pruned = builder(phrase).threads(threads).build().unwrap();
+            let unpruned = builder(phrase)
+                .threads(threads)
+                .prune(false)
+                .build()
+                .unwrap();
+            assert_eq!(anagrams(&unpruned), anagrams(&pruned));
+            assert_eq!(0, unpruned.prune_stats().dead);
+        }
+    }
+    // "et" leaves "ee", which no words use up
+    let anagrammer = builder("tee e").threads(1).build().unwrap();
+    assert!(anagrams(&anagrammer).is_empty());
+    assert!(anagrammer.prune_stats().dead > 0);
+    // word counts are more than pruning knows about
+    let anagrammer = builder("tea cat").minimum_words(3).build().unwrap();
+    assert_eq!(vec!["a cat et"], anagrams(&anagrammer));
+    assert_eq!(0, anagrammer.prune_stats().dead);
+}
+
+#[test]
+fn pruning_with_floors() {
+    // what sorted searches find dead above a floor is not dead for others
+    for phrase in &["tea cat eat", "tee tat cat", "tea tea cat"] {
+        let anagrammer = builder(phrase).build().unwrap();
+        let expected = anagrammer.anagrams().count();
+        let sorted = || anagrammer.sorted(Order::Lexicographic).unwrap().count();
+        assert_eq!(expected, sorted());
+        assert_eq!(expected, anagrammer.anagrams().count());
+        assert_eq!(expected, sorted());
+        assert_eq!(expected, sorted());
+        assert_eq!(expected, anagrammer.top(1000, Combiner::Sum).count());
+    }
+}
+
+#[test]
+fn rarest_letter() {
+    let rarest = |b: AnagrammerBuilder| b.strategy(Strategy::RarestLetter);
+    let cases: Vec<Box<dyn Fn() -> AnagrammerBuilder>> = vec![
+        Box::new(|| builder("tea cat")),
+        Box::new(|| builder("tea cat eat")),
+        Box::new(|| builder("tea cat tee").threads(1)),
+        Box::new(|| builder("tea cat").include("cat")),
+        Box::new(|| builder("tea cat eat").minimum_words(3).maximum_words(4)),
+        Box::new(|| builder("tea cat eat").require_match("^e", 2)),
+        Box::new(|| builder("tea cat eat").prune(false)),
+    ];
+    for case in &cases {
+        let expected = anagrams(&case().build().unwrap());
+        assert!(!expected.is_empty());
+        assert_eq!(expected, anagrams(&rarest(case()).build().unwrap()));
+        let sorted = |b: AnagrammerBuilder| -> Vec<String> {
+            b.build()
+                .unwrap()
+                .sorted(Order::Lexicographic)
+                .unwrap()
+                .map(|a| a.to_string())
+                .collect()
+        };
+        assert_eq!(sorted(case()), sorted(rarest(case())));
+    }
+    let anagrammer = rarest(builder("tea cat eat")).build().unwrap();
+    let all = anagrammer.anagrams_by_score(Combiner::Sum);
+    let top: Vec<Anagram> = anagrammer.top(4, Combiner::Sum).collect();
+    assert_eq!(4, top.len());
+    assert_eq!(all[0].score(Combiner::Sum), top[0].score(Combiner::Sum));
+    let error = |b: AnagrammerBuilder| b.build().err().unwrap().exit_code();
+    assert_eq!(2, error(rarest(builder("te?"))));
+    assert_eq!(2, error(rarest(builder("teat").leftover(1))));
+    assert_eq!(2, error(rarest(builder("tea").pattern("3"))));
+}
+
+#[test]
+fn letter_order() {
+    let expected = anagrams(&builder("tea cat").build().unwrap());
+    for order in &["rarity", "alphabetical", "cat", "zeta"] {
+        let order = order.parse::<LetterOrder>().unwrap();
+        for &strategy in &[Strategy::SortKey, Strategy::RarestLetter] {
+            let anagrammer = builder("tea cat")
+                .letter_order(order.clone())
+                .strategy(strategy)
+                .build()
+                .unwrap();
+            assert_eq!(expected, anagrams(&anagrammer));
+        }
+    }
+    let (translator, arena) = dictionary::parse(&WORDS.join("\n")).unwrap();
+    assert_eq!(vec!['t', 'a', 'e', 'c'], translator.alphabet());
+    let (translator, reordered) = dictionary::reorder(translator, arena, &LetterOrder::Rarity);
+    assert_eq!(vec!['c', 'a', 'e', 't'], translator.alphabet());
+    for word in WORDS {
+        assert!(reordered.contains(&translator.translate(word).unwrap()));
+    }
+    assert!(!reordered.contains(&translator.translate("tat").unwrap()));
+}
@@ -0,0 +1,13 @@
+extern crate ranagrams;
+
+use ranagrams::cache::parse_size;
+
+#[test]
+fn sizes() {
+    assert_eq!(Ok(4096), parse_size("4096"));
+    assert_eq!(Ok(512 << 10), parse_size("512K"));
+    assert_eq!(Ok(100 << 20), parse_size("100mb"));
+    assert_eq!(Ok(2 << 30), parse_size("2GiB"));
+    assert!(parse_size("12X").is_err());
+    assert!(parse_size("M").is_err());
+}
@@ -1,6 +1,90 @@
 extern crate ranagrams;
 
+use std::process::{Command, Output};
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rana"))
+        .arg("-d")
+        .arg(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/wordlists/english.txt"
+        ))
+        .args(args)
+        .output()
+        .expect("could not run rana")
+}
+
+fn rana(args: &[&str]) -> Vec<String> {
+    let output = run(args);
+    assert!(output.status.success());
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(String::from)
+        .collect()
+}
+
+fn rana_status(args: &[&str]) -> Option<i32> {
+    run(args).status.code()
+}
+
+#[test]
+fn rotten_apple() {
+    // the count given in the long help
+    assert_eq!(2695, rana(&["rotten", "apple"]).len());
+}
+
+#[test]
+fn limit() {
+    assert_eq!(10, rana(&["-l", "10", "rotten", "apple"]).len());
+}
+
+#[test]
+fn required_matches() {
+    let found = rana(&["--require-match", "2:^p", "rotten", "apple"]);
+    assert!(!found.is_empty());
+    for anagram in &found {
+        assert!(anagram.split(' ').filter(|w| w.starts_with('p')).count() >= 2);
+    }
+}
+
+#[test]
+fn leftovers() {
+    let found = rana(&["--leftover", "1", "rotten", "apples"]);
+    // the anagrams of "rotten apple" are "rotten apples" with an s left over
+    let left: Vec<&String> = found.iter().filter(|a| a.ends_with(" [+s]")).collect();
+    assert_eq!(2695, left.len());
+}
+
+#[test]
+fn blanks() {
+    // "rotten apple" with its last letter unknown
+    let found = rana(&["rotten", "appl?"]);
+    assert_eq!(30575, found.len());
+    for anagram in &found {
+        assert_eq!(1, anagram.chars().filter(|c| c.is_uppercase()).count());
+    }
+}
+
 #[test]
-fn foo() {
-    assert!(true)
+fn strategy_with_leftovers() {
+    let found = rana(&[
+        "--strategy",
+        "sort-key",
+        "--leftover",
+        "1",
+        "rotten",
+        "apples",
+    ]);
+    assert_eq!(2695, found.iter().filter(|a| a.ends_with(" [+s]")).count());
+    // the rarest letter strategy is still refused, with a usage error
+    let status = rana_status(&[
+        "--strategy",
+        "rarest-letter",
+        "--leftover",
+        "1",
+        "rotten",
+        "apples",
+    ]);
+    assert_eq!(Some(2), status);
 }
@@ -0,0 +1,31 @@
+extern crate ranagrams;
+
+use ranagrams::letter_order::LetterOrder;
+
+#[test]
+fn names() {
+    for order in &[
+        LetterOrder::Frequency,
+        LetterOrder::Rarity,
+        LetterOrder::Alphabetical,
+        LetterOrder::Custom(vec!['q', 'z']),
+    ] {
+        assert_eq!(Ok(order.clone()), order.to_string().parse());
+    }
+    assert_eq!(Ok(LetterOrder::Custom(vec!['q', 'z'])), "QZ".parse());
+    assert!("qzq".parse::<LetterOrder>().is_err());
+    assert!("q-z".parse::<LetterOrder>().is_err());
+    assert!("".parse::<LetterOrder>().is_err());
+    assert_eq!(LetterOrder::Frequency, LetterOrder::default());
+}
+
+#[test]
+fn arrange() {
+    let counts = || vec![('t', 2), ('a', 3), ('e', 2), ('c', 1)];
+    let arranged = |order: LetterOrder| order.arrange(counts()).into_iter().collect::<String>();
+    // ties are broken alphabetically
+    assert_eq!("aetc", arranged(LetterOrder::Frequency));
+    assert_eq!("ceta", arranged(LetterOrder::Rarity));
+    assert_eq!("acet", arranged(LetterOrder::Alphabetical));
+    assert_eq!("tcae", arranged(LetterOrder::Custom(vec!['t', 'z', 'c'])));
+}
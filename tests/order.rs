@@ -0,0 +1,12 @@
+extern crate ranagrams;
+
+use ranagrams::order::Order;
+
+#[test]
+fn names() {
+    for &order in &[Order::Lexicographic, Order::FewestWords, Order::LongestWord] {
+        assert_eq!(Ok(order), order.to_string().parse());
+    }
+    assert!("alphabetical".parse::<Order>().is_err());
+    assert_eq!(Order::Lexicographic, Order::default());
+}
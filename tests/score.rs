@@ -0,0 +1,53 @@
+extern crate ranagrams;
+
+use ranagrams::score::{Combiner, Tally};
+
+const COMBINERS: &[Combiner] = &[Combiner::Sum, Combiner::Minimum, Combiner::GeometricMean];
+
+#[test]
+fn names() {
+    for &combiner in COMBINERS {
+        assert_eq!(Ok(combiner), combiner.to_string().parse());
+    }
+    assert!("mean".parse::<Combiner>().is_err());
+    assert_eq!(Combiner::GeometricMean, Combiner::default());
+}
+
+#[test]
+fn tallies() {
+    let weights = [4.0, 1.0, 0.5, 2.0];
+    let tally = weights.iter().fold(Tally::new(), |t, &w| t.add(w));
+    for &combiner in COMBINERS {
+        let combined = combiner.combine(&weights);
+        assert!((tally.score(combiner) - combined).abs() < 1e-9);
+        assert_eq!(0.0, Tally::new().score(combiner));
+    }
+    assert_eq!(7.5, tally.score(Combiner::Sum));
+    assert_eq!(0.5, tally.score(Combiner::Minimum));
+    assert!((tally.score(Combiner::GeometricMean) - 4f64.powf(0.25)).abs() < 1e-9);
+    // a weight which is not positive zeroes the geometric mean
+    assert_eq!(0.0, tally.add(0.0).score(Combiner::GeometricMean));
+}
+
+#[test]
+fn bounds() {
+    let start = [3.0, 0.5].iter().fold(Tally::new(), |t, &w| t.add(w));
+    // every way of adding one to three words weighing at most 2 scores no
+    // more than the bound
+    let choices = [0.25, 1.0, 2.0];
+    for &combiner in COMBINERS {
+        let bound = start.bound(combiner, 2.0, 3);
+        let mut tallies = vec![start];
+        for _ in 0..3 {
+            tallies = tallies
+                .iter()
+                .flat_map(|t| choices.iter().map(move |&w| t.add(w)))
+                .collect();
+            for t in &tallies {
+                assert!(t.score(combiner) <= bound + 1e-9);
+            }
+        }
+    }
+    assert_eq!(9.5, start.bound(Combiner::Sum, 2.0, 3));
+    assert_eq!(0.5, start.bound(Combiner::Minimum, 2.0, 3));
+}
@@ -0,0 +1,48 @@
+extern crate ranagrams;
+
+use ranagrams::template::{Slot, Template};
+
+fn slot(shortest: usize, longest: Option<usize>) -> Slot {
+    Slot { shortest, longest }
+}
+
+#[test]
+fn parsing() {
+    let template: Template = "5 3-4,_ 2-".parse().unwrap();
+    assert_eq!(
+        &[
+            slot(5, Some(5)),
+            slot(3, Some(4)),
+            slot(1, None),
+            slot(2, None)
+        ],
+        template.slots()
+    );
+    assert_eq!("5 3-4 _ 2-", template.to_string());
+    assert_eq!(Ok(template), "5 3-4 _ 2-".parse());
+    let enumeration: Template = "(5,3,6)".parse().unwrap();
+    assert_eq!("5 3 6", enumeration.to_string());
+    for bad in &["", "()", "x", "0", "4-2", "3--", "-3"] {
+        assert!(bad.parse::<Template>().is_err(), "{:?} parsed", bad);
+    }
+}
+
+#[test]
+fn room() {
+    let template: Template = "2-3 _ 4".parse().unwrap();
+    assert_eq!(1, template.shortest());
+    assert_eq!(None, template.longest());
+    // the slots from the first on need at least 7 letters
+    assert!(!template.room(0, 6));
+    assert!(template.room(0, 7));
+    assert!(template.room(0, 100));
+    // the last needs exactly 4
+    assert!(template.room(2, 4));
+    assert!(!template.room(2, 5));
+    // nothing is left over once every slot is filled
+    assert!(template.room(3, 0));
+    assert!(!template.room(3, 1));
+    let bounded: Template = "2-3 4".parse().unwrap();
+    assert_eq!(Some(4), bounded.longest());
+    assert!(!bounded.room(0, 8));
+}
@@ -0,0 +1,116 @@
+extern crate ranagrams;
+
+use ranagrams::dictionary;
+use ranagrams::trie::{Trie, TrieArena};
+use std::sync::Arc;
+
+#[test]
+fn minimization() {
+    let (translator, arena) =
+        dictionary::parse("walk\nwalking\ntalk\ntalking\ntalked\nwalked").unwrap();
+    let stats = arena.stats();
+    // both initial letters lead to the same node, and "-ing" and "-ed" end in
+    // the same leaf
+    assert_eq!(9, stats.nodes);
+    assert_eq!(19, stats.trie_nodes);
+    assert!(stats.bytes < stats.trie_bytes);
+    for word in &["walk", "walking", "talk", "talking", "talked", "walked"] {
+        assert!(arena.contains(&translator.translate(word).unwrap()));
+    }
+    assert!(!arena.contains(&translator.translate("tal").unwrap()));
+    // removal leaves the words sharing the removed word's suffix intact
+    let mut trie = Trie::new(arena, translator, false, false);
+    let copy = trie.clone();
+    trie.remove(&trie.translator.translate("walked").unwrap());
+    assert!(!trie
+        .arena
+        .contains(&trie.translator.translate("walked").unwrap()));
+    assert!(trie
+        .arena
+        .contains(&trie.translator.translate("walk").unwrap()));
+    assert!(trie
+        .arena
+        .contains(&trie.translator.translate("talked").unwrap()));
+    // nor does it disturb clones
+    assert!(copy
+        .arena
+        .contains(&copy.translator.translate("walked").unwrap()));
+}
+
+#[test]
+fn layout() {
+    let (translator, arena) = dictionary::parse("tea\nte\nat\neat\nate").unwrap();
+    let root = arena.root();
+    assert_eq!(arena.nodes().len() - 1, root);
+    for (i, node) in arena.nodes().iter().enumerate() {
+        let children = arena.children(i);
+        // edges are in the translator's order and point to earlier nodes
+        assert!(children.windows(2).all(|w| w[0].character < w[1].character));
+        assert!(children.iter().all(|e| (e.target as usize) < i));
+        assert_eq!((node.end - node.start) as usize, children.len());
+    }
+    // removing every word leaves an empty root
+    let mut arena = arena;
+    for word in &["tea", "te", "at", "eat", "ate"] {
+        arena.remove(&translator.translate(word).unwrap());
+    }
+    assert!(arena.children(arena.root()).is_empty());
+    assert!(!arena.nodes()[arena.root()].terminal);
+    assert_eq!(1, arena.stats().nodes);
+}
+
+#[test]
+fn weights() {
+    let (translator, mut arena) =
+        dictionary::parse("tea\t3\nte\nat\t0.5\neat\t2\nate\t1\nat\t0.25").unwrap();
+    let weight = |arena: &TrieArena, word: &str| arena.weight(&translator.translate(word).unwrap());
+    // a repeated word keeps its greatest weight, and unweighted words weigh 1
+    assert_eq!(
+        vec![3.0, 1.0, 0.5, 2.0, 1.0],
+        ["tea", "te", "at", "eat", "ate"]
+            .iter()
+            .map(|w| weight(&arena, w))
+            .collect::<Vec<_>>()
+    );
+    // the other words keep their weights when one is removed
+    arena.remove(&translator.translate("eat").unwrap());
+    assert_eq!(3.0, weight(&arena, "tea"));
+    assert_eq!(1.0, weight(&arena, "ate"));
+    assert_eq!(0.5, weight(&arena, "at"));
+    // weights survive compilation
+    let path = std::env::temp_dir().join(format!("rana-weights-{}.rana", std::process::id()));
+    let path = path.to_str().unwrap();
+    dictionary::write(path, &translator, &arena).unwrap();
+    let (translator, loaded) = dictionary::load(path).unwrap();
+    assert_eq!(3.0, loaded.weight(&translator.translate("tea").unwrap()));
+    assert_eq!(0.5, loaded.weight(&translator.translate("at").unwrap()));
+    std::fs::remove_file(path).ok();
+    assert!(dictionary::parse("tea\tlots").is_err());
+}
+
+#[test]
+fn shared_words() {
+    let (translator, arena) = dictionary::parse("tea\nte\nat\neat\nate\ncat").unwrap();
+    let trie = Trie::new(arena, translator, true, false);
+    let cc = trie.translator.count("teaat").unwrap();
+    let optimized = trie.optimize(&cc, 1, None, &[]);
+    let cc = Arc::new(cc);
+    let words = optimized.words_for(cc.clone(), &[], &true);
+    let extensions = optimized.extensions_for(cc.clone(), &[], &true);
+    assert_eq!(words, extensions.iter().cloned().collect::<Vec<_>>());
+    // an offset into a list rather than a copy of its words
+    assert!(Arc::ptr_eq(
+        &extensions.list,
+        &optimized.cache.get(&cc).unwrap()
+    ));
+    // every list holds the same copy of a word
+    let at = trie.translator.translate("at").unwrap();
+    let (word, residual) = words.iter().find(|(w, _)| w[..] == at[..]).unwrap();
+    let again = optimized.words_for(residual.clone(), &[], &true);
+    let (other, _) = again.iter().find(|(w, _)| w[..] == at[..]).unwrap();
+    assert!(Arc::ptr_eq(word, other));
+    let key = trie.translator.translate("eat").unwrap();
+    let later = optimized.extensions_for(cc, &key, &true);
+    assert!(later.iter().all(|(w, _)| w[..] >= key[..]));
+    assert!(later.len() < extensions.len());
+}